@@ -4,6 +4,7 @@ mod pocket;
 use pocket::*;
 
 use pocket_knife_frontend::*;
+use pocket_knife_file_format::StdArchive;
 
 use i_slint_backend_winit::winit::dpi::{LogicalSize, Size};
 use i_slint_backend_winit::winit::event::{ElementState, Event, KeyEvent, WindowEvent};
@@ -38,12 +39,13 @@ fn main() {
     let surface_texture = SurfaceTexture::new(window_size.width, window_size.height, &window);
     let pixels = Rc::new(RefCell::new(Pixels::new(SCREEN_WIDTH, SCREEN_HEIGHT, surface_texture).unwrap()));
 
-    let filesystem_image = Rc::new(RefCell::new(File::open(args[1].clone()).unwrap()));
+    let filesystem_image = Rc::new(RefCell::new(StdArchive(File::open(args[1].clone()).unwrap())));
 
     let interact_values = Rc::new(RefCell::new(load_interact_values().map(|value| Interact { value, changed: true })));
 
     let pocket = Pocket {
         pixels,
+        archive_path: args[1].clone(),
         filesystem_image,
         interact_values,
     };
@@ -1,15 +1,18 @@
 use pocket_knife_frontend::{Backend, SCREEN_PIXELS};
+use pocket_knife_file_format::StdArchive;
 
 use chrono::{offset::Local, NaiveDateTime};
-use i_slint_core::{software_renderer::{MinimalSoftwareWindow, Rgb565Pixel}, platform::Platform};
+use i_slint_core::{software_renderer::{MinimalSoftwareWindow, Rgb565Pixel}, platform::Platform, api::PlatformError};
 use pixels::Pixels;
 use rgb565::Rgb565;
-use std::{rc::Rc, time::{SystemTime, Duration}, fs::File, io::{Read, Seek}, cell::RefCell};
+use std::{rc::Rc, time::{SystemTime, Duration}, fs::File, cell::RefCell};
 
 #[derive(Clone)]
 pub struct Pocket {
     pub pixels: Rc<RefCell<Pixels>>,
-    pub filesystem_image: Rc<RefCell<File>>,
+    // volumes are next to the archive, see open_volume
+    pub archive_path: String,
+    pub filesystem_image: Rc<RefCell<StdArchive<File>>>,
     pub interact_values: Rc<RefCell<[Interact; 16]>>,
 }
 
@@ -29,11 +32,11 @@ impl Backend for Pocket {
         println!("{}", message);
     }
 
-    fn slint_platform(window: Rc<MinimalSoftwareWindow>) -> Box<dyn Platform + 'static> {
-        Box::new(SlintPlatform {
+    fn slint_platform(window: Rc<MinimalSoftwareWindow>) -> Result<Box<dyn Platform + 'static>, PlatformError> {
+        Ok(Box::new(SlintPlatform {
             window,
             start_time: SystemTime::now(),
-        })
+        }))
     }
 
     fn blit(&self, buffer: [Rgb565Pixel; SCREEN_PIXELS]) {
@@ -61,6 +64,12 @@ impl Backend for Pocket {
     fn now(&self) -> NaiveDateTime {
         Local::now().naive_local()
     }
+
+    // named like the manager writes them, "<archive>.<number>"
+    fn open_volume(&self, number: u16) -> Result<Self, Self::Error> {
+        let volume = File::open(format!("{}.{}", self.archive_path, number))?;
+        Ok(Pocket { filesystem_image: Rc::new(RefCell::new(StdArchive(volume))), ..self.clone() })
+    }
 }
 
 impl embedded_io::ErrorType for Pocket {
@@ -73,13 +82,13 @@ impl embedded_io::Read for Pocket {
     }
 
     fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), embedded_io::ReadExactError<Self::Error>> {
-        self.filesystem_image.borrow_mut().read_exact(buf).map_err(From::from)
+        self.filesystem_image.borrow_mut().read_exact(buf)
     }
 }
 
 impl embedded_io::Seek for Pocket {
     fn seek(&mut self, pos: embedded_io::SeekFrom) -> Result<u64, Self::Error> {
-        self.filesystem_image.borrow_mut().seek(pos.into())
+        self.filesystem_image.borrow_mut().seek(pos)
     }
 
     fn rewind(&mut self) -> Result<(), Self::Error> {
@@ -1,4 +1,5 @@
 use alloc::string::String;
+use core::fmt::{self, Display, Formatter};
 
 #[derive(Debug, Clone)]
 pub struct Error(pub String);
@@ -8,3 +9,11 @@ impl embedded_io::Error for Error {
         embedded_io::ErrorKind::Other
     }
 }
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl core::error::Error for Error {}
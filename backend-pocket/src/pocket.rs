@@ -15,12 +15,19 @@ use litex_openfpga::{println, SlintPlatform, UART, File};
 use litex_pac::Peripherals;
 use litex_pac::constants::{CONFIG_CLOCK_FREQUENCY, VIDEO_FRAMEBUFFER_BASE};
 use slint::platform::Platform;
+use slint::PlatformError;
 use slint::platform::software_renderer::{MinimalSoftwareWindow, Rgb565Pixel};
 
 pub const FRAMEBUFFER_ADDRESS: *mut Rgb565Pixel = VIDEO_FRAMEBUFFER_BASE as *mut Rgb565Pixel;
 
+// the archive is in data slot 1, and any volumes it's split into in the slots after it (see data.json)
+pub const ARCHIVE_SLOT: u32 = 1;
+// how many volume slots data.json declares
+pub const VOLUME_SLOTS: u16 = 4;
+
 #[derive(Clone)]
 pub struct Pocket {
+    slot: u32,
     seek_position: Rc<RefCell<u32>>,
 }
 
@@ -29,11 +36,11 @@ impl Backend for Pocket {
         println!("{}", message);
     }
 
-    fn slint_platform(window: Rc<MinimalSoftwareWindow>) -> Box<dyn Platform + 'static> {
-        Box::new(SlintPlatform::new(
+    fn slint_platform(window: Rc<MinimalSoftwareWindow>) -> Result<Box<dyn Platform + 'static>, PlatformError> {
+        Ok(Box::new(SlintPlatform::new(
             window,
             CONFIG_CLOCK_FREQUENCY,
-        ))
+        )))
     }
 
     fn blit(&self, buffer: [Rgb565Pixel; SCREEN_PIXELS]) {
@@ -98,23 +105,36 @@ impl Backend for Pocket {
         let time = rtc.unix_seconds.read().unix_seconds().bits();
         NaiveDateTime::from_timestamp_opt(time as i64, 0).unwrap()
     }
+
+    fn open_volume(&self, number: u16) -> Result<Self, Self::Error> {
+        if number == 0 || number > VOLUME_SLOTS {
+            return Err(Error(format!("volume {} has no data slot, only volumes 1 to {} do", number, VOLUME_SLOTS)));
+        }
+        Ok(Pocket { slot: ARCHIVE_SLOT + number as u32, seek_position: Rc::new(RefCell::new(0)) })
+    }
 }
 
 // never does partial reads
 impl Read for Pocket {
     fn read(&mut self, buffer: &mut [u8]) -> Result<usize, Self::Error> {
+        let position = *self.seek_position.borrow();
+        // the APF file API takes 32-bit offsets and lengths, so the whole read has to end below 4 GiB
+        let length = u32::try_from(buffer.len()).ok()
+            .filter(|&length| position.checked_add(length).is_some())
+            .ok_or_else(|| Error(format!("can't read {} bytes at {:#X}, past what the APF file API can address", buffer.len(), position)))?;
+
         println!(
             "read: buffer address {:X?}, length {}, data address {:X?}",
             buffer as *mut [u8],
-            buffer.len(),
-            *self.seek_position.borrow(),
+            length,
+            position,
         );
 
         File::request_read(
-            *self.seek_position.borrow(),
-            buffer.len() as u32,
+            position,
+            length,
             buffer as *mut [u8] as *mut u8 as u32,
-            1,
+            self.slot,
         );
 
         println!("read requested");
@@ -123,7 +143,7 @@ impl Read for Pocket {
 
         println!("buffer bytes: {:X?}", buffer);
 
-        *self.seek_position.borrow_mut() += buffer.len() as u32;
+        *self.seek_position.borrow_mut() = position + length;
 
         Ok(buffer.len())
     }
@@ -134,16 +154,18 @@ impl Read for Pocket {
     }
 }
 
+// positions that don't fit in the APF file API's 32-bit offsets are errors, instead of wrapping around to
+// somewhere else in the file. the file's own size is 32-bit too, so this is everything FileTable::read accepts
 impl Seek for Pocket {
     fn seek(&mut self, target: SeekFrom) -> Result<u64, Self::Error> {
         let mut seek_position = self.seek_position.borrow_mut();
-        *seek_position = match target {
-            SeekFrom::Current(offset) => seek_position.wrapping_add_signed(offset as i32),
-            SeekFrom::Start(offset) => offset as u32,
-            SeekFrom::End(offset) => {
-                File::size(1).wrapping_add_signed(offset as i32)
-            },
+        let position = match target {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::Current(offset) => (*seek_position as u64).checked_add_signed(offset),
+            SeekFrom::End(offset) => (File::size(self.slot) as u64).checked_add_signed(offset),
         };
+        *seek_position = position.and_then(|position| u32::try_from(position).ok())
+            .ok_or_else(|| Error(format!("can't seek to {:?}, outside what the APF file API can address", target)))?;
         Ok(*seek_position as u64)
     }
 }
@@ -155,6 +177,7 @@ impl ErrorType for Pocket {
 impl Default for Pocket {
     fn default() -> Self {
         Pocket {
+            slot: ARCHIVE_SLOT,
             seek_position: Rc::new(RefCell::new(0)),
         }
     }
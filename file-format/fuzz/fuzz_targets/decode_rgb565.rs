@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use pocket_knife_file_format::Rgb565Image;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = Rgb565Image::decode(data);
+});
@@ -0,0 +1,22 @@
+#![no_main]
+
+use embedded_io::Read;
+use libfuzzer_sys::fuzz_target;
+use pocket_knife_file_format::FileTable;
+use pocket_knife_file_format_fuzz::SliceArchive;
+
+// anything FileTable::read accepts has to be safe to open in every way the frontend does
+fuzz_target!(|data: &[u8]| {
+    let mut archive = SliceArchive::new(data);
+    let Ok(file_table) = FileTable::read(&mut archive) else { return };
+
+    let _ = file_table.verify(&mut archive);
+    for filename in file_table.0.keys() {
+        let _ = file_table.open_file(&mut archive, filename.clone());
+        let _ = file_table.open_thumbnail(&mut archive, filename.clone());
+        if let Ok(mut reader) = file_table.open_entry(&mut archive, filename.clone()) {
+            let mut buffer = [0u8; 4096];
+            while let Ok(1..) = reader.read(&mut buffer) {}
+        }
+    }
+});
@@ -0,0 +1,9 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use pocket_knife_file_format::Header;
+use pocket_knife_file_format_fuzz::SliceArchive;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = Header::read(&mut SliceArchive::new(data));
+});
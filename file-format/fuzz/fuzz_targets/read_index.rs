@@ -0,0 +1,18 @@
+#![no_main]
+
+use core::convert::Infallible;
+use libfuzzer_sys::fuzz_target;
+use pocket_knife_file_format::FileTableView;
+use pocket_knife_file_format_fuzz::SliceArchive;
+
+fuzz_target!(|data: &[u8]| {
+    let mut archive = SliceArchive::new(data);
+    let Ok((header, index)) = FileTableView::read_index(&mut archive) else { return };
+    let Ok(view) = FileTableView::new::<Infallible>(&header, &index) else { return };
+
+    for entry in view.iter() {
+        let _ = entry.metadata();
+        assert!(view.find(entry.filename()).is_some());
+        let _ = view.open_file(&mut archive, entry.filename());
+    }
+});
@@ -0,0 +1,40 @@
+use core::convert::Infallible;
+use embedded_io::{ErrorType, Read, Seek, SeekFrom};
+
+// an archive held in memory, so fuzz inputs can be read without touching the filesystem
+pub struct SliceArchive<'a> {
+    pub bytes: &'a [u8],
+    pub position: u64,
+}
+
+impl <'a> SliceArchive<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        SliceArchive { bytes, position: 0 }
+    }
+}
+
+impl <'a> ErrorType for SliceArchive<'a> {
+    type Error = Infallible;
+}
+
+impl <'a> Read for SliceArchive<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Infallible> {
+        let start = self.position.min(self.bytes.len() as u64) as usize;
+        let remaining = &self.bytes[start..];
+        let length = remaining.len().min(buf.len());
+        buf[..length].copy_from_slice(&remaining[..length]);
+        self.position += length as u64;
+        Ok(length)
+    }
+}
+
+impl <'a> Seek for SliceArchive<'a> {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, Infallible> {
+        self.position = match pos {
+            SeekFrom::Start(position) => position,
+            SeekFrom::Current(offset) => self.position.saturating_add_signed(offset),
+            SeekFrom::End(offset) => (self.bytes.len() as u64).saturating_add_signed(offset),
+        };
+        Ok(self.position)
+    }
+}
@@ -0,0 +1,136 @@
+use crate::{AnimationError, ImageEncodeError, Rgb565Encoding, Rgb565Header, Rgb565Image};
+
+use alloc::{vec, vec::Vec};
+use embedded_io::{Read, Seek, SeekFrom};
+
+// animations, as a sequence of whole frames each shown for its own time:
+//
+// 0  .. 4     magic "PANI"
+// 4  .. 8     width
+// 8  .. 12    height
+// 12 .. 16    how many times to play through, 0 = forever
+// 16 .. 20    frame count
+// 20 .. ?     frame directory, in playing order: each frame's duration in milliseconds, then its offset from the
+//             start of the animation and its length, as u32s
+// ?  .. end   frames, each a whole rgb565 image without padding (see rgb565.rs) the size of the animation.
+//             frames are stored as they're shown, so nothing is drawn on top of the one before
+//
+// like tiled images, frames are found by seeking, so entries holding animations have to be stored without
+// compression to be read a frame at a time
+
+pub const ANIMATION_MEDIA_TYPE: &str = "image/x-pocket-knife-animation";
+
+pub const ANIMATION_MAGIC: &[u8; 4] = b"PANI";
+
+pub const ANIMATION_HEADER_LENGTH: u64 = 20;
+
+const FRAME_RECORD_LENGTH: u64 = 12;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct AnimationFrame {
+    pub image: Rgb565Image,
+    // milliseconds
+    pub duration: u32,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Animation {
+    pub width: u32,
+    pub height: u32,
+    pub plays: u32,
+    // duration, offset and length of every frame
+    frames: Vec<(u32, u32, u32)>,
+}
+
+impl Animation {
+    // every frame has to be width by height. offsets are u32s, so the whole thing has to fit in 4 GiB
+    pub fn encode(
+        width: u32,
+        height: u32,
+        plays: u32,
+        frames: &[AnimationFrame],
+        encoding: Rgb565Encoding,
+    ) -> Result<Vec<u8>, ImageEncodeError> {
+        let images: Vec<Vec<u8>> = frames.iter().map(|frame| frame.image.encode(encoding)).collect();
+        let directory_end = ANIMATION_HEADER_LENGTH + frames.len() as u64 * FRAME_RECORD_LENGTH;
+        let length = directory_end + images.iter().map(|image| image.len() as u64).sum::<u64>();
+        if length > u32::MAX as u64 {
+            return Err(ImageEncodeError::TooLarge(length));
+        }
+
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(ANIMATION_MAGIC);
+        for value in [width, height, plays, frames.len() as u32] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        let mut offset = directory_end as u32;
+        for (frame, image) in frames.iter().zip(images.iter()) {
+            bytes.extend_from_slice(&frame.duration.to_le_bytes());
+            bytes.extend_from_slice(&offset.to_le_bytes());
+            bytes.extend_from_slice(&(image.len() as u32).to_le_bytes());
+            offset += image.len() as u32;
+        }
+        for image in images.iter() {
+            bytes.extend_from_slice(image);
+        }
+        Ok(bytes)
+    }
+
+    // reads the header and frame directory, from the start of the animation
+    pub fn read<R: Read + Seek>(reader: &mut R) -> Result<Animation, AnimationError<R::Error>> {
+        let length = reader.seek(SeekFrom::End(0)).map_err(AnimationError::Seek)?;
+        reader.seek(SeekFrom::Start(0)).map_err(AnimationError::Seek)?;
+
+        let mut header = [0u8; ANIMATION_HEADER_LENGTH as usize];
+        reader.read_exact(&mut header).map_err(AnimationError::Read)?;
+        if header[0..4] != *ANIMATION_MAGIC {
+            return Err(AnimationError::NotAnimation);
+        }
+        let u32_at = |bytes: &[u8], offset: usize| u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+        let [width, height, plays, count] = [4, 8, 12, 16].map(|offset| u32_at(&header, offset));
+
+        // make sure the directory is really there before allocating anything for it
+        let directory_length = Some(count as u64 * FRAME_RECORD_LENGTH)
+            .filter(|&directory_length| directory_length <= length.saturating_sub(ANIMATION_HEADER_LENGTH))
+            .and_then(|directory_length| usize::try_from(directory_length).ok())
+            .ok_or(AnimationError::InvalidDirectory)?;
+        let mut directory = vec![0u8; directory_length];
+        reader.read_exact(&mut directory).map_err(AnimationError::Read)?;
+        let frames: Vec<(u32, u32, u32)> = directory.chunks_exact(FRAME_RECORD_LENGTH as usize)
+            .map(|frame| (u32_at(frame, 0), u32_at(frame, 4), u32_at(frame, 8)))
+            .collect();
+        if frames.iter().any(|&(_, offset, frame_length)| offset as u64 + frame_length as u64 > length) {
+            return Err(AnimationError::InvalidDirectory);
+        }
+
+        Ok(Animation { width, height, plays, frames })
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    // milliseconds
+    pub fn duration(&self, frame: usize) -> Option<u32> {
+        self.frames.get(frame).map(|&(duration, _, _)| duration)
+    }
+
+    pub fn read_frame<R: Read + Seek>(&self, reader: &mut R, frame: usize) -> Result<Rgb565Image, AnimationError<R::Error>> {
+        let &(_, offset, length) = self.frames.get(frame).ok_or(AnimationError::NoSuchFrame(frame))?;
+        let mut bytes = vec![0u8; length as usize];
+        reader.seek(SeekFrom::Start(offset as u64)).map_err(AnimationError::Seek)?;
+        reader.read_exact(&mut bytes).map_err(AnimationError::Read)?;
+
+        // a frame that isn't the size of the animation would leave parts of the one before showing, so it isn't
+        // worth decoding
+        let header = Rgb565Header::decode(&bytes).map_err(AnimationError::Decode)?;
+        if header.width != self.width || header.height != self.height || header.stride != self.width {
+            return Err(AnimationError::InvalidFrame(frame));
+        }
+        Rgb565Image::decode(&bytes).map_err(AnimationError::Decode)
+    }
+}
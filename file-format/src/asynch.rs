@@ -0,0 +1,106 @@
+use crate::*;
+
+use embedded_io::ReadExactError;
+use embedded_io_async::{Read, Seek};
+
+// async counterparts of FileTable::read, open_file and read_entry, so a backend can get on with something else
+// while the archive is being read. they make the same reads in the same order as the blocking versions, and share
+// every check and limit with them
+
+impl Header {
+    pub async fn read_async<A: Read>(archive: &mut A) -> Result<Header, ReadError<A::Error>> {
+        let mut signature = [0u8; SIGNATURE.len()];
+        archive.read_exact(&mut signature).await.map_err(ReadError::NoSignature)?;
+        check_signature(signature)?;
+
+        // version 0 has a table address where the version should be
+        let version = read_u64(archive).await.map_err(ReadError::ReadVersion)?;
+        if version >= LEGACY_HEADER_LENGTH {
+            return Ok(Header::version_0(version));
+        }
+        check_version(version)?;
+
+        let features = Features {
+            required: read_u32(archive).await.map_err(ReadError::ReadFeatures)?,
+            optional: read_u32(archive).await.map_err(ReadError::ReadFeatures)?,
+        };
+        check_features(features)?;
+
+        let mut header = Header::new(features, 0);
+        header.version = version;
+        header.table_address = read_u64(archive).await.map_err(ReadError::ReadFileTableAddress)?;
+        if version >= 2 {
+            header.table_length = read_u64(archive).await.map_err(ReadError::ReadFileTableLength)?;
+            header.table_checksum = read_u32(archive).await.map_err(ReadError::ReadFileTableChecksum)?;
+        }
+        if version >= 6 {
+            header.alignment = read_u32(archive).await.map_err(ReadError::ReadAlignment)?;
+        }
+        Ok(header)
+    }
+
+    pub async fn read_trailer_async<A: Read + Seek>(&mut self, archive: &mut A) -> Result<(), ReadError<A::Error>> {
+        archive.seek(SeekFrom::End(-(TRAILER_LENGTH as i64))).await.map_err(ReadError::SeekToTrailer)?;
+        let mut trailer = [0u8; TRAILER_LENGTH as usize];
+        archive.read_exact(&mut trailer).await.map_err(ReadError::ReadTrailer)?;
+        self.set_trailer(&trailer);
+        Ok(())
+    }
+}
+
+impl FileTable {
+    pub async fn read_async<A: Read + Seek>(archive: &mut A) -> Result<FileTable, ReadError<A::Error>> {
+        let mut header = Header::read_async(archive).await?;
+        if header.has_trailer() {
+            header.read_trailer_async(archive).await?;
+        }
+
+        let archive_length = archive.seek(SeekFrom::End(0)).await.map_err(ReadError::GetArchiveLength)?;
+        legacy::set_table_length_v1(&mut header, archive_length);
+        check_table_bounds(&header, archive_length)?;
+        archive.seek(SeekFrom::Start(header.table_address)).await.map_err(ReadError::SeekToFileTable)?;
+
+        let mut table_bytes = vec![0u8; header.table_length as usize];
+        archive.read_exact(&mut table_bytes).await.map_err(ReadError::ReadFileTable)?;
+        if header.version >= 2 {
+            check_table_checksum(&header, &table_bytes)?;
+        }
+        decode_table(&header, &table_bytes)
+    }
+
+    pub async fn open_file_async<A: Read + Seek>(
+        &self,
+        archive: &mut A,
+        filename: String,
+    ) -> Result<Vec<u8>, OpenError<A::Error>> {
+        let entry = self.0.get(&filename).ok_or(OpenError::NoSuchFile(filename.clone()))?;
+        let archive = volume(core::slice::from_mut(archive), &filename, entry.volume)?;
+        read_entry_async(archive, filename, entry).await
+    }
+}
+
+pub async fn read_entry_async<A: Read + Seek>(
+    archive: &mut A,
+    filename: String,
+    entry: &Entry,
+) -> Result<Vec<u8>, OpenError<A::Error>> {
+    check_decompressed_length(&filename, entry)?;
+    let archive_length = archive.seek(SeekFrom::End(0)).await.map_err(OpenError::GetArchiveLength)?;
+    check_entry_bounds(&filename, entry.offset, entry.length, archive_length)?;
+    let mut buffer = vec![0u8; entry.length as usize];
+    archive.seek(SeekFrom::Start(entry.offset)).await.map_err(|err| OpenError::SeekToStart(filename.clone(), err))?;
+    archive.read_exact(&mut buffer).await.map_err(|err| OpenError::ReadFile(filename.clone(), err))?;
+    decode_entry(filename, entry, buffer)
+}
+
+async fn read_u32<A: Read>(archive: &mut A) -> Result<u32, ReadExactError<A::Error>> {
+    let mut bytes = [0u8; 4];
+    archive.read_exact(&mut bytes).await?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+async fn read_u64<A: Read>(archive: &mut A) -> Result<u64, ReadExactError<A::Error>> {
+    let mut bytes = [0u8; 8];
+    archive.read_exact(&mut bytes).await?;
+    Ok(u64::from_le_bytes(bytes))
+}
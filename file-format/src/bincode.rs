@@ -1,21 +1,8 @@
-pub use bincode::{Decode, Encode, decode_from_reader, encode_into_writer};
+pub use bincode::{Decode, Encode, decode_from_slice, encode_to_vec};
 
-use alloc::format;
-use bincode::{de::read::Reader, enc::write::Writer, error::{EncodeError, DecodeError}};
-use embedded_io::{Read, Write};
+use bincode::config::{Configuration, LittleEndian, Limit, Varint};
 
-pub const BINCODE_CONFIG: bincode::config::Configuration = bincode::config::standard();
+// caps how much memory decoding a table can claim, however many entries it says it has
+pub(crate) const DECODE_LIMIT: usize = 64 * 1024 * 1024;
 
-pub struct BincodeAdapter<'a, A>(pub &'a mut A);
-
-impl <'a, A: Read> Reader for BincodeAdapter<'a, A> {
-    fn read(&mut self, bytes: &mut [u8]) -> Result<(), DecodeError> {
-        self.0.read_exact(bytes).map_err(|err| DecodeError::OtherString(format!("{:?}", err)))
-    }
-}
-
-impl <'a, A: Write> Writer for BincodeAdapter<'a, A> {
-    fn write(&mut self, bytes: &[u8]) -> Result<(), EncodeError> {
-        self.0.write_all(bytes).map_err(|err| EncodeError::OtherString(format!("{:?}", err)))
-    }
-}
+pub const BINCODE_CONFIG: Configuration<LittleEndian, Varint, Limit<DECODE_LIMIT>> = bincode::config::standard().with_limit::<DECODE_LIMIT>();
@@ -0,0 +1,167 @@
+use crate::*;
+
+use core::cell::RefCell;
+
+// builds an archive one entry at a time, so entries can come from different kinds of sources and be added as
+// they become ready. errors mean the same thing as they do for FileTable::create, and a rejected filename
+// doesn't write anything, so the builder can keep going after one
+pub struct ArchiveBuilder<'a, A> {
+    archive: &'a mut A,
+    header: Header,
+    layout: Layout,
+    table: BTreeMap<String, Entry>,
+}
+
+// what add_bytes and add_reader store alongside an entry, the same things an Archivable can provide
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct EntryOptions {
+    pub compression: Compression,
+    pub metadata: Metadata,
+    pub thumbnail: Option<ThumbnailImage>,
+}
+
+impl <'a, A: Write + Seek> ArchiveBuilder<'a, A> {
+    pub fn new(archive: &'a mut A, options: CreateOptions) -> Result<Self, CreateError<A::Error>> {
+        // write a placeholder header, we don't know the table address yet
+        ArchiveBuilder::start(archive, 0, options)
+    }
+
+    pub fn finish(mut self) -> Result<FileTable, CreateError<A::Error>> {
+        write_table(self.archive, self.layout.position, &mut self.header, &self.table)?;
+        rewrite_header(self.archive, &self.header)?;
+        Ok(FileTable(self.table))
+    }
+}
+
+impl <'a, A: Write> ArchiveBuilder<'a, A> {
+    // never seeks, so the archive can go straight to a pipe or a socket. the table's location goes in a trailer
+    // at the end instead of the header
+    pub fn new_streaming(archive: &'a mut A, options: CreateOptions) -> Result<Self, CreateError<A::Error>> {
+        ArchiveBuilder::start(archive, REQUIRED_FEATURE_TRAILER, options)
+    }
+
+    // only for builders from new_streaming, anything else has to go back and fill in its header
+    pub fn finish_streaming(mut self) -> Result<FileTable, CreateError<A::Error>> {
+        if !self.header.has_trailer() {
+            return Err(CreateError::NotStreaming);
+        }
+        write_table(self.archive, self.layout.position, &mut self.header, &self.table)?;
+        Ok(FileTable(self.table))
+    }
+
+    fn start(archive: &'a mut A, required_features: u32, options: CreateOptions) -> Result<Self, CreateError<A::Error>> {
+        validate_options(&options)?;
+        let index = if options.index { REQUIRED_FEATURE_INDEX } else { 0 };
+        let features = Features { required: required_features | index, optional: 0 };
+        let header = Header::new(features, options.alignment);
+        header.write(archive).map_err(CreateError::WriteHeader)?;
+        let layout = Layout::new(header.length(), options.alignment, options.deduplicate);
+        Ok(ArchiveBuilder { archive, header, layout, table: BTreeMap::new() })
+    }
+
+    pub fn table(&self) -> &BTreeMap<String, Entry> {
+        &self.table
+    }
+
+    pub fn add_entry<I: Archivable<A>>(&mut self, input_file: &I) -> Result<(), CreateError<A::Error>> {
+        add_entry(self.archive, &mut self.layout, &mut self.table, input_file)
+    }
+
+    pub fn add_bytes(
+        &mut self,
+        filename: String,
+        bytes: &[u8],
+        options: EntryOptions,
+    ) -> Result<(), CreateError<A::Error>> {
+        self.add_entry(&BytesInput { filename, bytes, options })
+    }
+
+    pub fn add_reader<R: Read>(
+        &mut self,
+        filename: String,
+        reader: &mut R,
+        options: EntryOptions,
+    ) -> Result<(), CreateError<A::Error>> where A::Error: From<R::Error> {
+        self.add_entry(&ReaderInput { filename, reader: RefCell::new(reader), options })
+    }
+}
+
+struct BytesInput<'b> {
+    filename: String,
+    bytes: &'b [u8],
+    options: EntryOptions,
+}
+
+impl <'b, T: ErrorType> Archivable<T> for BytesInput<'b> {
+    fn filename(&self) -> Result<String, T::Error> {
+        Ok(self.filename.clone())
+    }
+
+    fn write_into<W: Write<Error = T::Error>>(&self, archive: &mut W) -> Result<u64, T::Error> {
+        archive.write_all(self.bytes)?;
+        Ok(self.bytes.len() as u64)
+    }
+
+    fn compression(&self) -> Compression {
+        self.options.compression
+    }
+
+    fn metadata(&self) -> Result<Metadata, T::Error> {
+        Ok(self.options.metadata.clone())
+    }
+
+    fn thumbnail(&self) -> Result<Option<ThumbnailImage>, T::Error> {
+        Ok(self.options.thumbnail.clone())
+    }
+}
+
+// a filename and its contents, stored as they are
+impl <T: ErrorType, N: AsRef<str>, B: AsRef<[u8]>> Archivable<T> for (N, B) {
+    fn filename(&self) -> Result<String, T::Error> {
+        Ok(self.0.as_ref().into())
+    }
+
+    fn write_into<W: Write<Error = T::Error>>(&self, archive: &mut W) -> Result<u64, T::Error> {
+        archive.write_all(self.1.as_ref())?;
+        Ok(self.1.as_ref().len() as u64)
+    }
+}
+
+// write_into only gets &self, but reading needs the reader mutably
+struct ReaderInput<'b, R> {
+    filename: String,
+    reader: RefCell<&'b mut R>,
+    options: EntryOptions,
+}
+
+impl <'b, R: Read, T: ErrorType> Archivable<T> for ReaderInput<'b, R> where T::Error: From<R::Error> {
+    fn filename(&self) -> Result<String, T::Error> {
+        Ok(self.filename.clone())
+    }
+
+    fn write_into<W: Write<Error = T::Error>>(&self, archive: &mut W) -> Result<u64, T::Error> {
+        let mut reader = self.reader.borrow_mut();
+        let mut buffer = [0u8; 4096];
+        let mut length = 0;
+        loop {
+            let read = reader.read(&mut buffer)?;
+            if read == 0 {
+                return Ok(length);
+            }
+            archive.write_all(&buffer[..read])?;
+            length += read as u64;
+        }
+    }
+
+    fn compression(&self) -> Compression {
+        self.options.compression
+    }
+
+    fn metadata(&self) -> Result<Metadata, T::Error> {
+        Ok(self.options.metadata.clone())
+    }
+
+    fn thumbnail(&self) -> Result<Option<ThumbnailImage>, T::Error> {
+        Ok(self.options.thumbnail.clone())
+    }
+}
@@ -0,0 +1,38 @@
+use crc32fast::Hasher;
+use embedded_io::{ErrorType, Write};
+
+pub fn checksum(bytes: &[u8]) -> u32 {
+    crc32fast::hash(bytes)
+}
+
+// passes writes through to the archive, keeping a running checksum of everything written
+pub struct ChecksumWriter<'a, A> {
+    archive: &'a mut A,
+    hasher: Hasher,
+}
+
+impl <'a, A: Write> ChecksumWriter<'a, A> {
+    pub fn new(archive: &'a mut A) -> Self {
+        ChecksumWriter { archive, hasher: Hasher::new() }
+    }
+
+    pub fn checksum(self) -> u32 {
+        self.hasher.finalize()
+    }
+}
+
+impl <'a, A: ErrorType> ErrorType for ChecksumWriter<'a, A> {
+    type Error = A::Error;
+}
+
+impl <'a, A: Write> Write for ChecksumWriter<'a, A> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        let written = self.archive.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.archive.flush()
+    }
+}
@@ -0,0 +1,80 @@
+use crate::{Decode, Encode, DecompressError};
+
+use alloc::{vec, vec::Vec};
+use core::marker::PhantomData;
+use embedded_io::{ErrorType, Write};
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default, Decode, Encode)]
+pub enum Compression {
+    #[default]
+    None,
+    Lz4,
+}
+
+impl Compression {
+    // returns None if compressing doesn't make the data any smaller
+    pub fn compress(self, bytes: &[u8]) -> Option<Vec<u8>> {
+        let compressed = match self {
+            Compression::None => return None,
+            Compression::Lz4 => lz4_flex::block::compress(bytes),
+        };
+        if compressed.len() < bytes.len() {
+            Some(compressed)
+        } else {
+            None
+        }
+    }
+
+    // the most a compressed entry of this length can decompress to, anything claiming more is corrupt
+    pub fn max_decompressed_length(self, length: u64) -> u64 {
+        match self {
+            Compression::None => length,
+            // every lz4 sequence takes at least one byte per 255 bytes it produces
+            Compression::Lz4 => length.saturating_mul(255),
+        }
+    }
+
+    pub fn decompress(self, bytes: Vec<u8>, decompressed_length: u64) -> Result<Vec<u8>, DecompressError> {
+        match self {
+            Compression::None => Ok(bytes),
+            Compression::Lz4 => {
+                let mut decompressed = vec![0u8; decompressed_length as usize];
+                let length = lz4_flex::block::decompress_into(&bytes, &mut decompressed).map_err(DecompressError::Lz4)?;
+                if length as u64 != decompressed_length {
+                    return Err(DecompressError::WrongLength { expected: decompressed_length, found: length as u64 });
+                }
+                Ok(decompressed)
+            },
+        }
+    }
+}
+
+// collects a whole input in memory so it can be compressed before it goes into the archive
+pub struct BufferWriter<E>(pub Vec<u8>, PhantomData<E>);
+
+impl <E> BufferWriter<E> {
+    pub fn new() -> Self {
+        BufferWriter(Vec::new(), PhantomData)
+    }
+}
+
+impl <E> Default for BufferWriter<E> {
+    fn default() -> Self {
+        BufferWriter::new()
+    }
+}
+
+impl <E: embedded_io::Error> ErrorType for BufferWriter<E> {
+    type Error = E;
+}
+
+impl <E: embedded_io::Error> Write for BufferWriter<E> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, E> {
+        self.0.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), E> {
+        Ok(())
+    }
+}
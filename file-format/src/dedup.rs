@@ -0,0 +1,30 @@
+use crate::*;
+
+// entries written with deduplication can share their data: they have the same volume, offset and length, so
+// reading any of them gives the same bytes
+
+impl FileTable {
+    // every group of filenames that share their data, in filename order
+    pub fn shared_entries(&self) -> Vec<Vec<&str>> {
+        let mut groups: BTreeMap<(u16, u64, u64), Vec<&str>> = BTreeMap::new();
+        // empty entries don't have any data to share, even if they happen to have the same offset
+        for (filename, entry) in self.0.iter().filter(|(_, entry)| entry.length > 0) {
+            groups.entry((entry.volume, entry.offset, entry.length)).or_default().push(filename);
+        }
+        let mut shared: Vec<Vec<&str>> = groups.into_values().filter(|group| group.len() > 1).collect();
+        shared.sort();
+        shared
+    }
+
+    // how many bytes sharing saves compared to storing every entry and thumbnail separately
+    pub fn shared_length(&self) -> u64 {
+        let mut ranges = BTreeMap::new();
+        for entry in self.0.values() {
+            *ranges.entry((entry.volume, entry.offset, entry.length)).or_insert(0u64) += 1;
+            if let Some(thumbnail) = entry.thumbnail {
+                *ranges.entry((entry.volume, thumbnail.offset, thumbnail.length())).or_insert(0u64) += 1;
+            }
+        }
+        ranges.into_iter().map(|((_, _, length), count)| length * (count - 1)).sum()
+    }
+}
@@ -0,0 +1,74 @@
+use crate::{CreateError, Entry, FileTable};
+
+use alloc::{collections::btree_map::BTreeMap, format, string::String, vec::Vec};
+use core::ops::Bound;
+
+// directories aren't stored on their own, they exist wherever a filename has a separator in it:
+// "covers/vol1.bmp" is the file "vol1.bmp" in the directory "covers". the root directory is "".
+
+pub const PATH_SEPARATOR: char = '/';
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Child<'a> {
+    File(&'a Entry),
+    Directory,
+}
+
+impl FileTable {
+    // immediate children of a directory, in filename order
+    pub fn children(&self, directory: &str) -> Vec<(&str, Child<'_>)> {
+        let prefix = directory_prefix(directory);
+        let mut children: Vec<(&str, Child)> = Vec::new();
+        for (path, entry) in self.0.range::<str, _>((Bound::Included(prefix.as_str()), Bound::Unbounded)) {
+            let Some(relative_path) = path.strip_prefix(prefix.as_str()) else { break };
+            let child = match relative_path.split_once(PATH_SEPARATOR) {
+                None => (relative_path, Child::File(entry)),
+                Some((name, _)) => (name, Child::Directory),
+            };
+            // everything in a subdirectory sorts together, so only the first file in one matters
+            if children.last() != Some(&child) {
+                children.push(child);
+            }
+        }
+        children
+    }
+
+    pub fn is_directory(&self, path: &str) -> bool {
+        path.is_empty() || contains_directory(&self.0, path)
+    }
+}
+
+fn contains_directory(table: &BTreeMap<String, Entry>, path: &str) -> bool {
+    let prefix = directory_prefix(path);
+    table.range::<str, _>((Bound::Included(prefix.as_str()), Bound::Unbounded))
+        .next()
+        .is_some_and(|(filename, _)| filename.starts_with(prefix.as_str()))
+}
+
+fn directory_prefix(directory: &str) -> String {
+    if directory.is_empty() {
+        String::new()
+    } else {
+        format!("{}{}", directory, PATH_SEPARATOR)
+    }
+}
+
+pub(crate) fn validate_path<E>(path: &str) -> Result<(), CreateError<E>> {
+    let valid = path.split(PATH_SEPARATOR).all(|component| !matches!(component, "" | "." | ".."));
+    if valid {
+        Ok(())
+    } else {
+        Err(CreateError::InvalidPath(path.into()))
+    }
+}
+
+// a path can't be used for a file and a directory at the same time
+pub(crate) fn check_path_conflicts<E>(table: &BTreeMap<String, Entry>, path: &str) -> Result<(), CreateError<E>> {
+    let parent_is_file = path.match_indices(PATH_SEPARATOR)
+        .any(|(index, _)| table.contains_key(&path[..index]));
+    if parent_is_file || contains_directory(table, path) {
+        Err(CreateError::PathConflict(path.into()))
+    } else {
+        Ok(())
+    }
+}
@@ -1,33 +1,522 @@
+use crate::SIGNATURE;
+
 use alloc::string::String;
+use alloc::vec::Vec;
 use bincode::error::{EncodeError, DecodeError};
-use embedded_io::ReadExactError;
+use core::error::Error;
+use core::fmt::{self, Display, Formatter};
+use embedded_io::{ErrorKind, ReadExactError, SeekFrom};
 
 #[derive(Debug)]
 pub enum ReadError<E> {
     NoSignature(ReadExactError<E>),
     InvalidSignature([u8; 20]),
+    ReadVersion(ReadExactError<E>),
+    UnsupportedVersion(u64),
+    ReadFeatures(ReadExactError<E>),
+    UnsupportedFeatures(u32),
     ReadFileTableAddress(ReadExactError<E>),
+    ReadFileTableLength(ReadExactError<E>),
+    ReadFileTableChecksum(ReadExactError<E>),
+    ReadAlignment(ReadExactError<E>),
+    SeekToTrailer(E),
+    ReadTrailer(ReadExactError<E>),
+    GetArchiveLength(E),
+    // the table has to sit between the header and the end of the archive
+    TableOutOfBounds { address: u64, length: u64 },
+    TableTooLarge(u64),
     SeekToFileTable(E),
+    ReadFileTable(ReadExactError<E>),
+    InvalidTableChecksum { expected: u32, found: u32 },
     DeserializeFileTable(DecodeError),
+    // the index's records or strings don't fit in it, or aren't in order
+    InvalidIndex,
+    // FileTableView only reads archives written with an index
+    NoIndex,
+    // an entry or its thumbnail has to sit between the header and the table
+    EntryOutOfBounds(String),
+    InvalidDecompressedLength(String),
+    OpenVolume(u16, E),
+    ReadVolumeHeader(u16, ReadExactError<E>),
+    // a volume's header doesn't match the signature, a supported version or its own number
+    InvalidVolume(u16),
 }
 
 #[derive(Debug)]
 pub enum CreateError<E> {
-    SignatureWrite(E),
-    SkipAddress(E),
+    // alignments have to be a power of two
+    InvalidAlignment(u32),
+    WriteHeader(E),
     InvalidFilename(E),
     DuplicateFilename(String),
-    GetOffset(E),
+    InvalidPath(String),
+    PathConflict(String),
+    GetMetadata(String, E),
+    GetThumbnail(String, E),
+    AddThumbnail(String, E),
+    AddPadding(E),
     AddFile(String, E),
-    GetFileTableAddress(E),
     SerializeFileTable(EncodeError),
-    SeekToFileTableAddress(E),
-    WriteFileTableAddress(E),
+    TableTooLarge(u64),
+    WriteFileTable(E),
+    WriteTrailer(E),
+    SeekToHeader(E),
+    CreateVolume(u16, E),
+    // volume numbers only go up to u16::MAX
+    TooManyVolumes,
+    // an entry and its thumbnail have to fit in a single volume
+    EntryTooLarge(String),
+    // finish_streaming was called on a builder that wasn't from new_streaming
+    NotStreaming,
 }
 
 #[derive(Debug)]
 pub enum OpenError<E> {
     NoSuchFile(String),
-    SeekToStart(E),
-    ReadFile(ReadExactError<E>),
+    NoThumbnail(String),
+    GetArchiveLength(E),
+    EntryOutOfBounds(String),
+    InvalidDecompressedLength(String),
+    SeekToStart(String, E),
+    ReadFile(String, ReadExactError<E>),
+    InvalidChecksum { filename: String, expected: u32, found: u32 },
+    Decompress(String, DecompressError),
+    // the entry is in a volume that wasn't given (see VolumeSet)
+    MissingVolume { filename: String, volume: u16 },
+}
+
+#[derive(Debug)]
+pub enum DecompressError {
+    Lz4(lz4_flex::block::DecompressError),
+    WrongLength { expected: u64, found: u64 },
+}
+
+#[derive(Debug)]
+pub enum Rgb565DecodeError {
+    // too short for a header, or the magic is wrong
+    NotRgb565,
+    UnknownEncoding(u8),
+    // rows can't be narrower than the image
+    InvalidStride { width: u32, stride: u32 },
+    // in pixels
+    WrongLength { expected: u64, found: u64 },
+    // a packet runs past the end of its row or of the data, or there's data left over
+    InvalidRle,
+    // in bytes, once decoded
+    TooLarge(u64),
+}
+
+#[derive(Debug)]
+pub enum TiledImageError<E> {
+    Seek(E),
+    Read(ReadExactError<E>),
+    NotTiled,
+    // zero-sized tiles, or a directory that doesn't fit in the image
+    InvalidDirectory,
+    NoSuchTile { column: u32, row: u32 },
+    // a tile isn't the size its place in the image needs
+    InvalidTile { column: u32, row: u32 },
+    Decode(Rgb565DecodeError),
+}
+
+// tiled images and animations find their parts by u32 offsets
+#[derive(Debug)]
+pub enum ImageEncodeError {
+    // in bytes
+    TooLarge(u64),
+}
+
+#[derive(Debug)]
+pub enum AnimationError<E> {
+    Seek(E),
+    Read(ReadExactError<E>),
+    NotAnimation,
+    // a directory that doesn't fit in the animation
+    InvalidDirectory,
+    NoSuchFrame(usize),
+    // a frame isn't the size of the animation
+    InvalidFrame(usize),
+    Decode(Rgb565DecodeError),
+}
+
+#[derive(Debug)]
+pub enum VerifyError<E> {
+    SeekToStart(String, E),
+    ReadFile(String, ReadExactError<E>),
+    CorruptEntries(Vec<String>),
+    MissingVolume(String, u16),
+}
+
+#[derive(Debug)]
+pub enum EntryReadError<E> {
+    Archive(E),
+    UnexpectedEof,
+    InvalidSeek(SeekFrom),
+    Decompress(DecompressError),
+}
+
+impl <E: embedded_io::Error> embedded_io::Error for EntryReadError<E> {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            EntryReadError::Archive(err) => err.kind(),
+            EntryReadError::UnexpectedEof => ErrorKind::Other,
+            EntryReadError::InvalidSeek(_) => ErrorKind::InvalidInput,
+            EntryReadError::Decompress(_) => ErrorKind::InvalidData,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum UpdateError<E> {
+    SeekToHeader(E),
+    ReadHeader(ReadError<E>),
+    // older versions with a shorter header, or a trailer, can't be upgraded in place (see update.rs)
+    OutdatedVersion(u64),
+    NoSuchFile(String),
+    SeekToEnd(E),
+    SeekToFile(String, E),
+    ReadFile(String, ReadExactError<E>),
+    Create(CreateError<E>),
+    // compacting writes a single file, which can't hold entries from other volumes
+    HasVolumes,
+}
+
+impl <E> From<CreateError<E>> for UpdateError<E> {
+    fn from(error: CreateError<E>) -> Self {
+        UpdateError::Create(error)
+    }
+}
+
+// messages only describe what went wrong at their own level, whatever caused it is left to source()
+
+impl <E> Display for ReadError<E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ReadError::NoSignature(_) => write!(f, "couldn't read the signature"),
+            ReadError::InvalidSignature(signature) => write!(f, "expected the signature \"{}\", found \"{}\"", SIGNATURE, signature.escape_ascii()),
+            ReadError::ReadVersion(_) => write!(f, "couldn't read the format version"),
+            ReadError::UnsupportedVersion(version) => write!(f, "format version {} isn't supported", version),
+            ReadError::ReadFeatures(_) => write!(f, "couldn't read the feature flags"),
+            ReadError::UnsupportedFeatures(features) => write!(f, "required features {:#x} aren't supported", features),
+            ReadError::ReadFileTableAddress(_) => write!(f, "couldn't read the table address"),
+            ReadError::ReadFileTableLength(_) => write!(f, "couldn't read the table length"),
+            ReadError::ReadFileTableChecksum(_) => write!(f, "couldn't read the table checksum"),
+            ReadError::ReadAlignment(_) => write!(f, "couldn't read the entry alignment"),
+            ReadError::SeekToTrailer(_) => write!(f, "couldn't seek to the trailer"),
+            ReadError::ReadTrailer(_) => write!(f, "couldn't read the trailer"),
+            ReadError::GetArchiveLength(_) => write!(f, "couldn't get the archive length"),
+            ReadError::TableOutOfBounds { address, length } => write!(f, "the table ({} bytes at offset {}) isn't inside the archive", length, address),
+            ReadError::TableTooLarge(length) => write!(f, "the table is too large ({} bytes)", length),
+            ReadError::SeekToFileTable(_) => write!(f, "couldn't seek to the table"),
+            ReadError::ReadFileTable(_) => write!(f, "couldn't read the table"),
+            ReadError::InvalidTableChecksum { expected, found } => write!(f, "expected the table checksum {:#010x}, found {:#010x}", expected, found),
+            ReadError::DeserializeFileTable(_) => write!(f, "couldn't decode the table"),
+            ReadError::InvalidIndex => write!(f, "the index is corrupt"),
+            ReadError::NoIndex => write!(f, "the archive has no index"),
+            ReadError::EntryOutOfBounds(filename) => write!(f, "{:?} isn't inside the archive", filename),
+            ReadError::InvalidDecompressedLength(filename) => write!(f, "{:?} can't decompress to its recorded length", filename),
+            ReadError::OpenVolume(number, _) => write!(f, "couldn't open volume {}", number),
+            ReadError::ReadVolumeHeader(number, _) => write!(f, "couldn't read the header of volume {}", number),
+            ReadError::InvalidVolume(number) => write!(f, "volume {} isn't a supported volume with that number", number),
+        }
+    }
+}
+
+impl <E> Display for CreateError<E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            CreateError::InvalidAlignment(alignment) => write!(f, "alignment {} isn't a power of two", alignment),
+            CreateError::WriteHeader(_) => write!(f, "couldn't write the header"),
+            CreateError::InvalidFilename(_) => write!(f, "couldn't get a filename"),
+            CreateError::DuplicateFilename(filename) => write!(f, "{:?} is already in the archive", filename),
+            CreateError::InvalidPath(filename) => write!(f, "{:?} isn't a valid path", filename),
+            CreateError::PathConflict(filename) => write!(f, "{:?} would be both a file and a directory", filename),
+            CreateError::GetMetadata(filename, _) => write!(f, "couldn't get the metadata for {:?}", filename),
+            CreateError::GetThumbnail(filename, _) => write!(f, "couldn't get the thumbnail for {:?}", filename),
+            CreateError::AddThumbnail(filename, _) => write!(f, "couldn't write the thumbnail for {:?}", filename),
+            CreateError::AddPadding(_) => write!(f, "couldn't write padding"),
+            CreateError::AddFile(filename, _) => write!(f, "couldn't write {:?}", filename),
+            CreateError::SerializeFileTable(_) => write!(f, "couldn't encode the table"),
+            CreateError::TableTooLarge(length) => write!(f, "the table is too large ({} bytes)", length),
+            CreateError::WriteFileTable(_) => write!(f, "couldn't write the table"),
+            CreateError::WriteTrailer(_) => write!(f, "couldn't write the trailer"),
+            CreateError::SeekToHeader(_) => write!(f, "couldn't seek to the header"),
+            CreateError::CreateVolume(number, _) => write!(f, "couldn't create volume {}", number),
+            CreateError::TooManyVolumes => write!(f, "the archive needs more than {} volumes", u16::MAX),
+            CreateError::EntryTooLarge(filename) => write!(f, "{:?} doesn't fit in a volume", filename),
+            CreateError::NotStreaming => write!(f, "the archive wasn't started for streaming, so it has no trailer to finish with"),
+        }
+    }
+}
+
+impl <E> Display for OpenError<E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            OpenError::NoSuchFile(filename) => write!(f, "no such file {:?}", filename),
+            OpenError::NoThumbnail(filename) => write!(f, "{:?} has no thumbnail", filename),
+            OpenError::GetArchiveLength(_) => write!(f, "couldn't get the archive length"),
+            OpenError::EntryOutOfBounds(filename) => write!(f, "{:?} isn't inside the archive", filename),
+            OpenError::InvalidDecompressedLength(filename) => write!(f, "{:?} can't decompress to its recorded length", filename),
+            OpenError::SeekToStart(filename, _) => write!(f, "couldn't seek to {:?}", filename),
+            OpenError::ReadFile(filename, _) => write!(f, "couldn't read {:?}", filename),
+            OpenError::InvalidChecksum { filename, expected, found } => write!(f, "expected {:?} to have the checksum {:#010x}, found {:#010x}", filename, expected, found),
+            OpenError::Decompress(filename, _) => write!(f, "couldn't decompress {:?}", filename),
+            OpenError::MissingVolume { filename, volume } => write!(f, "{:?} is in volume {}, which isn't open", filename, volume),
+        }
+    }
+}
+
+impl Display for DecompressError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            DecompressError::Lz4(_) => write!(f, "invalid lz4 data"),
+            DecompressError::WrongLength { expected, found } => write!(f, "expected {} bytes, found {}", expected, found),
+        }
+    }
+}
+
+impl Display for Rgb565DecodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Rgb565DecodeError::NotRgb565 => write!(f, "not an rgb565 image"),
+            Rgb565DecodeError::UnknownEncoding(encoding) => write!(f, "unknown rgb565 encoding {}", encoding),
+            Rgb565DecodeError::InvalidStride { width, stride } => write!(f, "stride {} is narrower than the width {}", stride, width),
+            Rgb565DecodeError::WrongLength { expected, found } => write!(f, "expected {} pixels, found {}", expected, found),
+            Rgb565DecodeError::InvalidRle => write!(f, "the rle data is corrupt"),
+            Rgb565DecodeError::TooLarge(length) => write!(f, "the image would take {} bytes to decode, which is too many", length),
+        }
+    }
+}
+
+impl <E> Display for TiledImageError<E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            TiledImageError::Seek(_) => write!(f, "couldn't seek in the image"),
+            TiledImageError::Read(_) => write!(f, "couldn't read the image"),
+            TiledImageError::NotTiled => write!(f, "not a tiled image"),
+            TiledImageError::InvalidDirectory => write!(f, "the tile directory is corrupt"),
+            TiledImageError::NoSuchTile { column, row } => write!(f, "no tile at column {}, row {}", column, row),
+            TiledImageError::InvalidTile { column, row } => write!(f, "the tile at column {}, row {} is the wrong size", column, row),
+            TiledImageError::Decode(_) => write!(f, "couldn't decode a tile"),
+        }
+    }
+}
+
+impl Display for ImageEncodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ImageEncodeError::TooLarge(length) => write!(f, "the image would take {} bytes, more than its offsets can reach", length),
+        }
+    }
+}
+
+impl <E> Display for AnimationError<E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            AnimationError::Seek(_) => write!(f, "couldn't seek in the animation"),
+            AnimationError::Read(_) => write!(f, "couldn't read the animation"),
+            AnimationError::NotAnimation => write!(f, "not an animation"),
+            AnimationError::InvalidDirectory => write!(f, "the frame directory is corrupt"),
+            AnimationError::NoSuchFrame(frame) => write!(f, "no frame {}", frame),
+            AnimationError::InvalidFrame(frame) => write!(f, "frame {} is the wrong size", frame),
+            AnimationError::Decode(_) => write!(f, "couldn't decode a frame"),
+        }
+    }
+}
+
+impl <E> Display for VerifyError<E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifyError::SeekToStart(filename, _) => write!(f, "couldn't seek to {:?}", filename),
+            VerifyError::ReadFile(filename, _) => write!(f, "couldn't read {:?}", filename),
+            VerifyError::CorruptEntries(filenames) => {
+                write!(f, "corrupt entries:")?;
+                for filename in filenames.iter() {
+                    write!(f, " {:?}", filename)?;
+                }
+                Ok(())
+            },
+            VerifyError::MissingVolume(filename, volume) => write!(f, "{:?} is in volume {}, which isn't open", filename, volume),
+        }
+    }
+}
+
+impl <E> Display for EntryReadError<E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            EntryReadError::Archive(_) => write!(f, "couldn't read the archive"),
+            EntryReadError::UnexpectedEof => write!(f, "the entry ended early"),
+            EntryReadError::InvalidSeek(position) => write!(f, "can't seek to {:?}", position),
+            EntryReadError::Decompress(_) => write!(f, "couldn't decompress the entry"),
+        }
+    }
+}
+
+impl <E> Display for UpdateError<E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            UpdateError::SeekToHeader(_) => write!(f, "couldn't seek to the header"),
+            UpdateError::ReadHeader(_) => write!(f, "couldn't read the header"),
+            UpdateError::OutdatedVersion(version) => write!(f, "archives from format version {} can't be changed in place, compact it first", version),
+            UpdateError::NoSuchFile(filename) => write!(f, "no such file {:?}", filename),
+            UpdateError::SeekToEnd(_) => write!(f, "couldn't seek to the end of the archive"),
+            UpdateError::SeekToFile(filename, _) => write!(f, "couldn't seek to {:?}", filename),
+            UpdateError::ReadFile(filename, _) => write!(f, "couldn't read {:?}", filename),
+            UpdateError::Create(_) => write!(f, "couldn't write the changes"),
+            UpdateError::HasVolumes => write!(f, "archives split into volumes can't be compacted"),
+        }
+    }
+}
+
+impl <E: Error + 'static> Error for ReadError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ReadError::NoSignature(err)
+            | ReadError::ReadVersion(err)
+            | ReadError::ReadFeatures(err)
+            | ReadError::ReadFileTableAddress(err)
+            | ReadError::ReadFileTableLength(err)
+            | ReadError::ReadFileTableChecksum(err)
+            | ReadError::ReadAlignment(err)
+            | ReadError::ReadTrailer(err)
+            | ReadError::ReadFileTable(err)
+            | ReadError::ReadVolumeHeader(_, err) => read_exact_source(err),
+            ReadError::SeekToTrailer(err)
+            | ReadError::GetArchiveLength(err)
+            | ReadError::SeekToFileTable(err)
+            | ReadError::OpenVolume(_, err) => Some(err),
+            ReadError::DeserializeFileTable(err) => bincode_source(err),
+            _ => None,
+        }
+    }
+}
+
+impl <E: Error + 'static> Error for CreateError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CreateError::WriteHeader(err)
+            | CreateError::InvalidFilename(err)
+            | CreateError::GetMetadata(_, err)
+            | CreateError::GetThumbnail(_, err)
+            | CreateError::AddThumbnail(_, err)
+            | CreateError::AddPadding(err)
+            | CreateError::AddFile(_, err)
+            | CreateError::WriteFileTable(err)
+            | CreateError::WriteTrailer(err)
+            | CreateError::SeekToHeader(err)
+            | CreateError::CreateVolume(_, err) => Some(err),
+            CreateError::SerializeFileTable(err) => bincode_source(err),
+            _ => None,
+        }
+    }
+}
+
+impl <E: Error + 'static> Error for OpenError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            OpenError::GetArchiveLength(err) | OpenError::SeekToStart(_, err) => Some(err),
+            OpenError::ReadFile(_, err) => read_exact_source(err),
+            OpenError::Decompress(_, err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl Error for DecompressError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DecompressError::Lz4(err) => Some(err),
+            DecompressError::WrongLength { .. } => None,
+        }
+    }
+}
+
+impl Error for Rgb565DecodeError {}
+
+impl <E: Error + 'static> Error for TiledImageError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TiledImageError::Seek(err) => Some(err),
+            TiledImageError::Read(err) => read_exact_source(err),
+            TiledImageError::Decode(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl Error for ImageEncodeError {}
+
+impl <E: Error + 'static> Error for AnimationError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            AnimationError::Seek(err) => Some(err),
+            AnimationError::Read(err) => read_exact_source(err),
+            AnimationError::Decode(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl <E: Error + 'static> Error for VerifyError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            VerifyError::SeekToStart(_, err) => Some(err),
+            VerifyError::ReadFile(_, err) => read_exact_source(err),
+            VerifyError::CorruptEntries(_) | VerifyError::MissingVolume(..) => None,
+        }
+    }
+}
+
+impl <E: Error + 'static> Error for EntryReadError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            EntryReadError::Archive(err) => Some(err),
+            EntryReadError::Decompress(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl <E: Error + 'static> Error for UpdateError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            UpdateError::SeekToHeader(err)
+            | UpdateError::SeekToEnd(err)
+            | UpdateError::SeekToFile(_, err) => Some(err),
+            UpdateError::ReadHeader(err) => Some(err),
+            UpdateError::ReadFile(_, err) => read_exact_source(err),
+            UpdateError::Create(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+// ReadExactError only implements Error with embedded-io's std feature, so what's inside it stands in for it
+fn read_exact_source<E: Error + 'static>(err: &ReadExactError<E>) -> Option<&(dyn Error + 'static)> {
+    match err {
+        ReadExactError::UnexpectedEof => Some(&UnexpectedEnd),
+        ReadExactError::Other(err) => Some(err),
+    }
+}
+
+#[derive(Debug)]
+struct UnexpectedEnd;
+
+impl Display for UnexpectedEnd {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "the archive ended early")
+    }
+}
+
+impl Error for UnexpectedEnd {}
+
+// the same goes for bincode's errors and its std feature
+#[cfg(feature = "std")]
+fn bincode_source<T: Error + 'static>(err: &T) -> Option<&(dyn Error + 'static)> {
+    Some(err)
+}
+
+#[cfg(not(feature = "std"))]
+fn bincode_source<T>(_: &T) -> Option<&(dyn Error + 'static)> {
+    None
 }
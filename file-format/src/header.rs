@@ -0,0 +1,209 @@
+use crate::ReadError;
+
+use embedded_io::{Read, ReadExactError, Seek, SeekFrom, Write};
+
+// 0  .. 20    signature
+// 20 .. 28    format version
+// 28 .. 32    required feature flags
+// 32 .. 36    optional feature flags
+// 36 .. 44    table address
+// 44 .. 52    table length (since version 2)
+// 52 .. 56    table checksum (since version 2)
+// 56 .. 60    entry alignment (since version 6)
+//
+// Version 0 archives were written before the header had a version field: they store the table
+// address directly after the signature and start their files at byte 28. Since a version 0 table
+// address can never point inside its own header, a value below 28 in the version field always
+// means a versioned header.
+//
+// Archives written without seeking (see new_streaming and finish_streaming in builder.rs) can't go back to
+// fill in the table address, so they set the trailer feature flag, leave the table fields in the header
+// zeroed and end with a trailer instead:
+//
+// 0  .. 8     table address
+// 8  .. 16    table length
+// 16 .. 20    table checksum
+//
+// Forward compatibility rules:
+// - a reader rejects any version newer than the one it was built with
+// - a reader rejects any required feature flag it doesn't know about
+// - a reader ignores any optional feature flag it doesn't know about
+
+pub const SIGNATURE: &str = "Pocket Knife Archive";
+
+pub const VERSION: u64 = 8;
+
+pub const LEGACY_HEADER_LENGTH: u64 = SIGNATURE.len() as u64 + 8;
+
+// tables longer than this get rejected before anything is allocated for them
+pub const MAX_TABLE_LENGTH: u64 = 16 * 1024 * 1024;
+
+pub const TRAILER_LENGTH: u64 = 8 + 8 + 4;
+
+// the table is found through the trailer instead of the header
+pub const REQUIRED_FEATURE_TRAILER: u32 = 1 << 0;
+
+// the table is an index (see index.rs) instead of bincode
+pub const REQUIRED_FEATURE_INDEX: u32 = 1 << 1;
+
+// entries can be in other volumes (see volume.rs), the archive itself only holds the table
+pub const REQUIRED_FEATURE_VOLUMES: u32 = 1 << 2;
+
+pub const KNOWN_REQUIRED_FEATURES: u32 = REQUIRED_FEATURE_TRAILER | REQUIRED_FEATURE_INDEX | REQUIRED_FEATURE_VOLUMES;
+pub const KNOWN_OPTIONAL_FEATURES: u32 = 0;
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
+pub struct Features {
+    pub required: u32,
+    pub optional: u32,
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Header {
+    pub version: u64,
+    pub features: Features,
+    pub table_address: u64,
+    // zero before version 2
+    pub table_length: u64,
+    pub table_checksum: u32,
+    // every entry and thumbnail starts at a multiple of this many bytes, zero if they're packed back-to-back
+    pub alignment: u32,
+}
+
+impl Header {
+    pub fn new(features: Features, alignment: u32) -> Header {
+        Header { version: VERSION, features, table_address: 0, table_length: 0, table_checksum: 0, alignment }
+    }
+
+    // where the first file starts
+    pub fn length(&self) -> u64 {
+        match self.version {
+            0 => LEGACY_HEADER_LENGTH,
+            1 => SIGNATURE.len() as u64 + 8 + 4 + 4 + 8,
+            2..=5 => SIGNATURE.len() as u64 + 8 + 4 + 4 + 8 + 8 + 4,
+            _ => SIGNATURE.len() as u64 + 8 + 4 + 4 + 8 + 8 + 4 + 4,
+        }
+    }
+
+    pub fn has_trailer(&self) -> bool {
+        self.features.required & REQUIRED_FEATURE_TRAILER != 0
+    }
+
+    pub fn has_index(&self) -> bool {
+        self.features.required & REQUIRED_FEATURE_INDEX != 0
+    }
+
+    pub fn has_volumes(&self) -> bool {
+        self.features.required & REQUIRED_FEATURE_VOLUMES != 0
+    }
+
+    pub fn write<A: Write>(&self, archive: &mut A) -> Result<(), A::Error> {
+        archive.write_all(SIGNATURE.as_bytes())?;
+        archive.write_all(&self.version.to_le_bytes())?;
+        archive.write_all(&self.features.required.to_le_bytes())?;
+        archive.write_all(&self.features.optional.to_le_bytes())?;
+        archive.write_all(&self.table_address.to_le_bytes())?;
+        archive.write_all(&self.table_length.to_le_bytes())?;
+        archive.write_all(&self.table_checksum.to_le_bytes())?;
+        archive.write_all(&self.alignment.to_le_bytes())?;
+        Ok(())
+    }
+
+    pub fn write_trailer<A: Write>(&self, archive: &mut A) -> Result<(), A::Error> {
+        archive.write_all(&self.table_address.to_le_bytes())?;
+        archive.write_all(&self.table_length.to_le_bytes())?;
+        archive.write_all(&self.table_checksum.to_le_bytes())?;
+        Ok(())
+    }
+
+    // fills in where the table is from the end of the archive
+    pub fn read_trailer<A: Read + Seek>(&mut self, archive: &mut A) -> Result<(), ReadError<A::Error>> {
+        archive.seek(SeekFrom::End(-(TRAILER_LENGTH as i64))).map_err(ReadError::SeekToTrailer)?;
+        let mut trailer = [0u8; TRAILER_LENGTH as usize];
+        archive.read_exact(&mut trailer).map_err(ReadError::ReadTrailer)?;
+        self.set_trailer(&trailer);
+        Ok(())
+    }
+
+    pub(crate) fn set_trailer(&mut self, trailer: &[u8; TRAILER_LENGTH as usize]) {
+        self.table_address = u64::from_le_bytes(trailer[0..8].try_into().unwrap());
+        self.table_length = u64::from_le_bytes(trailer[8..16].try_into().unwrap());
+        self.table_checksum = u32::from_le_bytes(trailer[16..20].try_into().unwrap());
+    }
+
+    pub fn read<A: Read>(archive: &mut A) -> Result<Header, ReadError<A::Error>> {
+        let mut signature = [0u8; SIGNATURE.len()];
+        archive.read_exact(&mut signature).map_err(ReadError::NoSignature)?;
+        check_signature(signature)?;
+
+        // version 0 has a table address where the version should be
+        let version = read_u64(archive).map_err(ReadError::ReadVersion)?;
+        if version >= LEGACY_HEADER_LENGTH {
+            return Ok(Header::version_0(version));
+        }
+        check_version(version)?;
+
+        let features = Features {
+            required: read_u32(archive).map_err(ReadError::ReadFeatures)?,
+            optional: read_u32(archive).map_err(ReadError::ReadFeatures)?,
+        };
+        check_features(features)?;
+
+        let table_address = read_u64(archive).map_err(ReadError::ReadFileTableAddress)?;
+
+        if version < 2 {
+            return Ok(Header { version, features, table_address, table_length: 0, table_checksum: 0, alignment: 0 });
+        }
+
+        let table_length = read_u64(archive).map_err(ReadError::ReadFileTableLength)?;
+        let table_checksum = read_u32(archive).map_err(ReadError::ReadFileTableChecksum)?;
+
+        if version < 6 {
+            return Ok(Header { version, features, table_address, table_length, table_checksum, alignment: 0 });
+        }
+
+        let alignment = read_u32(archive).map_err(ReadError::ReadAlignment)?;
+
+        Ok(Header { version, features, table_address, table_length, table_checksum, alignment })
+    }
+
+    pub(crate) fn version_0(table_address: u64) -> Header {
+        Header { version: 0, features: Features::default(), table_address, table_length: 0, table_checksum: 0, alignment: 0 }
+    }
+}
+
+// the checks Header::read makes along the way, shared with the async reader
+
+pub(crate) fn check_signature<E>(signature: [u8; SIGNATURE.len()]) -> Result<(), ReadError<E>> {
+    if signature != *SIGNATURE.as_bytes() {
+        return Err(ReadError::InvalidSignature(signature));
+    }
+    Ok(())
+}
+
+pub(crate) fn check_version<E>(version: u64) -> Result<(), ReadError<E>> {
+    if version == 0 || version > VERSION {
+        return Err(ReadError::UnsupportedVersion(version));
+    }
+    Ok(())
+}
+
+pub(crate) fn check_features<E>(features: Features) -> Result<(), ReadError<E>> {
+    let unknown_features = features.required & !KNOWN_REQUIRED_FEATURES;
+    if unknown_features != 0 {
+        return Err(ReadError::UnsupportedFeatures(unknown_features));
+    }
+    Ok(())
+}
+
+fn read_u32<A: Read>(archive: &mut A) -> Result<u32, ReadExactError<A::Error>> {
+    let mut bytes = [0u8; 4];
+    archive.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64<A: Read>(archive: &mut A) -> Result<u64, ReadExactError<A::Error>> {
+    let mut bytes = [0u8; 8];
+    archive.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
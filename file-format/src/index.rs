@@ -0,0 +1,297 @@
+use crate::*;
+
+use ::bincode::error::{DecodeError, EncodeError};
+use core::cmp::Ordering;
+
+// an index is a table laid out so it can be searched straight from its bytes, without decoding every entry
+// into the heap first:
+//
+// 0  .. 4     entry count
+// 4  .. ?     one record per entry, sorted by filename
+// ?  .. end   string blob: filenames, then each entry's metadata (bincode)
+//
+// every record is RECORD_LENGTH bytes:
+//
+// 0  .. 4     filename offset in the string blob
+// 4  .. 8     filename length
+// 8  .. 16    offset
+// 16 .. 24    length
+// 24 .. 32    decompressed length
+// 32 .. 36    checksum
+// 36 .. 37    compression: 0 = none, 1 = lz4
+// 37 .. 38    flags: 1 = has a checksum, 2 = has a thumbnail
+// 38 .. 40    volume
+// 40 .. 48    thumbnail offset
+// 48 .. 52    thumbnail width
+// 52 .. 56    thumbnail height
+// 56 .. 60    thumbnail checksum
+// 60 .. 64    metadata offset in the string blob
+// 64 .. 68    metadata length
+
+pub const RECORD_LENGTH: usize = 68;
+
+const FLAG_CHECKSUM: u8 = 1 << 0;
+const FLAG_THUMBNAIL: u8 = 1 << 1;
+
+#[derive(Debug, Copy, Clone)]
+pub struct FileTableView<'a> {
+    records: &'a [u8],
+    blob: &'a [u8],
+    // metadata is encoded differently in older versions
+    version: u64,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct EntryView<'a> {
+    record: &'a [u8],
+    blob: &'a [u8],
+    version: u64,
+}
+
+impl <'a> FileTableView<'a> {
+    // reads just the index of an archive, ready to be borrowed by FileTableView::new
+    pub fn read_index<A: Read + Seek>(archive: &mut A) -> Result<(Header, Vec<u8>), ReadError<A::Error>> {
+        let header = locate_table(archive)?;
+        if !header.has_index() {
+            return Err(ReadError::NoIndex);
+        }
+        let table_bytes = read_table_bytes(archive, &header)?;
+        Ok((header, table_bytes))
+    }
+
+    // checks every record up front, so nothing after this can go out of bounds
+    pub fn new<E>(header: &Header, bytes: &'a [u8]) -> Result<FileTableView<'a>, ReadError<E>> {
+        let count = bytes.get(0..4).map(|count| u32::from_le_bytes(count.try_into().unwrap())).ok_or(ReadError::InvalidIndex)?;
+        let records_end = (count as usize).checked_mul(RECORD_LENGTH)
+            .and_then(|length| length.checked_add(4))
+            .filter(|&records_end| records_end <= bytes.len())
+            .ok_or(ReadError::InvalidIndex)?;
+        let view = FileTableView { records: &bytes[4..records_end], blob: &bytes[records_end..], version: header.version };
+
+        let mut previous: Option<&str> = None;
+        for entry in view.iter() {
+            let filename = entry.checked_filename().ok_or(ReadError::InvalidIndex)?;
+            if entry.checked_metadata_bytes().is_none() || entry.compression_byte() > 1 {
+                return Err(ReadError::InvalidIndex);
+            }
+            if previous.is_some_and(|previous| previous >= filename) {
+                return Err(ReadError::InvalidIndex);
+            }
+            validate_entry(header, filename, &entry.entry_without_metadata())?;
+            previous = Some(filename);
+        }
+
+        Ok(view)
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len() / RECORD_LENGTH
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    // the entry at a position in filename order
+    pub fn get(&self, position: usize) -> Option<EntryView<'a>> {
+        let start = position.checked_mul(RECORD_LENGTH)?;
+        let record = self.records.get(start..start + RECORD_LENGTH)?;
+        Some(EntryView { record, blob: self.blob, version: self.version })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = EntryView<'a>> + 'a {
+        let (blob, version) = (self.blob, self.version);
+        self.records.chunks_exact(RECORD_LENGTH).map(move |record| EntryView { record, blob, version })
+    }
+
+    pub fn find(&self, filename: &str) -> Option<EntryView<'a>> {
+        let mut low = 0;
+        let mut high = self.len();
+        while low < high {
+            let middle = low + (high - low) / 2;
+            let entry = self.get(middle)?;
+            // str orders by bytes anyway, and this skips checking the utf-8 again
+            match entry.filename_bytes().cmp(filename.as_bytes()) {
+                Ordering::Less => low = middle + 1,
+                Ordering::Greater => high = middle,
+                Ordering::Equal => return Some(entry),
+            }
+        }
+        None
+    }
+
+    pub fn open_file<A: Read + Seek>(
+        &self,
+        archive: &mut A,
+        filename: &str,
+    ) -> Result<Vec<u8>, OpenError<A::Error>> {
+        let entry = self.find(filename).ok_or(OpenError::NoSuchFile(filename.into()))?;
+        let archive = volume(core::slice::from_mut(archive), filename, entry.volume())?;
+        read_entry(archive, filename.into(), &entry.entry_without_metadata())
+    }
+
+    // decodes everything, for when the whole table is needed after all
+    pub fn to_table<E>(&self) -> Result<FileTable, ReadError<E>> {
+        let mut table = BTreeMap::new();
+        for entry in self.iter() {
+            table.insert(entry.filename().into(), entry.entry().map_err(ReadError::DeserializeFileTable)?);
+        }
+        Ok(FileTable(table))
+    }
+}
+
+impl <'a> EntryView<'a> {
+    pub fn filename(&self) -> &'a str {
+        // FileTableView::new already made sure this is there
+        self.checked_filename().unwrap_or_default()
+    }
+
+    pub fn offset(&self) -> u64 {
+        self.u64_at(8)
+    }
+
+    pub fn length(&self) -> u64 {
+        self.u64_at(16)
+    }
+
+    pub fn decompressed_length(&self) -> u64 {
+        self.u64_at(24)
+    }
+
+    pub fn checksum(&self) -> Option<u32> {
+        (self.record[37] & FLAG_CHECKSUM != 0).then(|| self.u32_at(32))
+    }
+
+    pub fn compression(&self) -> Compression {
+        match self.compression_byte() {
+            1 => Compression::Lz4,
+            _ => Compression::None,
+        }
+    }
+
+    pub fn volume(&self) -> u16 {
+        u16::from_le_bytes(self.record[38..40].try_into().unwrap())
+    }
+
+    pub fn thumbnail(&self) -> Option<Thumbnail> {
+        (self.record[37] & FLAG_THUMBNAIL != 0).then(|| Thumbnail {
+            offset: self.u64_at(40),
+            width: self.u32_at(48),
+            height: self.u32_at(52),
+            checksum: self.u32_at(56),
+        })
+    }
+
+    pub fn metadata(&self) -> Result<Metadata, DecodeError> {
+        legacy::decode_metadata(self.version, self.checked_metadata_bytes().unwrap_or_default())
+    }
+
+    pub fn entry(&self) -> Result<Entry, DecodeError> {
+        Ok(Entry { metadata: self.metadata()?, ..self.entry_without_metadata() })
+    }
+
+    // for records read on their own (see PagedIndex), which only have their fixed-size fields
+    pub(crate) fn without_blob(record: &'a [u8], version: u64) -> EntryView<'a> {
+        EntryView { record, blob: &[], version }
+    }
+
+    // everything needed to open the entry, without allocating anything
+    pub(crate) fn entry_without_metadata(&self) -> Entry {
+        Entry {
+            offset: self.offset(),
+            length: self.length(),
+            checksum: self.checksum(),
+            compression: self.compression(),
+            decompressed_length: self.decompressed_length(),
+            metadata: Metadata::default(),
+            thumbnail: self.thumbnail(),
+            volume: self.volume(),
+        }
+    }
+
+    // offset and length in the string blob
+    pub(crate) fn filename_range(&self) -> (u32, u32) {
+        (self.u32_at(0), self.u32_at(4))
+    }
+
+    pub(crate) fn metadata_range(&self) -> (u32, u32) {
+        (self.u32_at(60), self.u32_at(64))
+    }
+
+    fn checked_filename(&self) -> Option<&'a str> {
+        let bytes = blob_range(self.blob, self.filename_range())?;
+        core::str::from_utf8(bytes).ok()
+    }
+
+    fn filename_bytes(&self) -> &'a [u8] {
+        blob_range(self.blob, self.filename_range()).unwrap_or_default()
+    }
+
+    fn checked_metadata_bytes(&self) -> Option<&'a [u8]> {
+        blob_range(self.blob, self.metadata_range())
+    }
+
+    fn compression_byte(&self) -> u8 {
+        self.record[36]
+    }
+
+    fn u32_at(&self, offset: usize) -> u32 {
+        u32::from_le_bytes(self.record[offset..offset + 4].try_into().unwrap())
+    }
+
+    fn u64_at(&self, offset: usize) -> u64 {
+        u64::from_le_bytes(self.record[offset..offset + 8].try_into().unwrap())
+    }
+}
+
+fn blob_range(blob: &[u8], (offset, length): (u32, u32)) -> Option<&[u8]> {
+    blob.get(offset as usize..(offset as usize).checked_add(length as usize)?)
+}
+
+pub(crate) fn encode_index(table: &BTreeMap<String, Entry>) -> Result<Vec<u8>, EncodeError> {
+    let mut records = Vec::with_capacity(4 + table.len() * RECORD_LENGTH);
+    records.extend_from_slice(&(table.len() as u32).to_le_bytes());
+
+    // filenames go first, so they sit close together for searching
+    let mut blob: Vec<u8> = table.keys().flat_map(|filename| filename.bytes()).collect();
+    let mut filename_offset = 0u32;
+
+    for (filename, entry) in table.iter() {
+        let metadata_offset = blob.len() as u32;
+        blob.extend_from_slice(&bincode::encode_to_vec(&entry.metadata, BINCODE_CONFIG)?);
+        let metadata_length = blob.len() as u32 - metadata_offset;
+
+        let compression: u8 = match entry.compression {
+            Compression::None => 0,
+            Compression::Lz4 => 1,
+        };
+        let mut flags = 0;
+        if entry.checksum.is_some() {
+            flags |= FLAG_CHECKSUM;
+        }
+        if entry.thumbnail.is_some() {
+            flags |= FLAG_THUMBNAIL;
+        }
+        let thumbnail = entry.thumbnail.unwrap_or(Thumbnail { offset: 0, width: 0, height: 0, checksum: 0 });
+
+        records.extend_from_slice(&filename_offset.to_le_bytes());
+        records.extend_from_slice(&(filename.len() as u32).to_le_bytes());
+        records.extend_from_slice(&entry.offset.to_le_bytes());
+        records.extend_from_slice(&entry.length.to_le_bytes());
+        records.extend_from_slice(&entry.decompressed_length.to_le_bytes());
+        records.extend_from_slice(&entry.checksum.unwrap_or(0).to_le_bytes());
+        records.extend_from_slice(&[compression, flags]);
+        records.extend_from_slice(&entry.volume.to_le_bytes());
+        records.extend_from_slice(&thumbnail.offset.to_le_bytes());
+        records.extend_from_slice(&thumbnail.width.to_le_bytes());
+        records.extend_from_slice(&thumbnail.height.to_le_bytes());
+        records.extend_from_slice(&thumbnail.checksum.to_le_bytes());
+        records.extend_from_slice(&metadata_offset.to_le_bytes());
+        records.extend_from_slice(&metadata_length.to_le_bytes());
+
+        filename_offset += filename.len() as u32;
+    }
+
+    records.extend_from_slice(&blob);
+    Ok(records)
+}
@@ -0,0 +1,77 @@
+use crate::*;
+
+use sha2::{Digest, Sha256};
+
+// where the next thing gets written in an archive. nothing gets written out of order, so there's no need to ask
+// the archive where it is
+pub(crate) struct Layout {
+    pub position: u64,
+    // see CreateOptions
+    pub alignment: u32,
+    // the offset of every block written so far by the sha-256 of its bytes, if identical blocks are being shared
+    written: Option<BTreeMap<[u8; 32], u64>>,
+}
+
+impl Layout {
+    pub fn new(position: u64, alignment: u32, deduplicate: bool) -> Layout {
+        Layout { position, alignment, written: deduplicate.then(BTreeMap::new) }
+    }
+
+    pub fn deduplicates(&self) -> bool {
+        self.written.is_some()
+    }
+
+    // where the next block would start after writing blocks of these lengths, as if none of them were shared
+    pub fn position_after(&self, lengths: impl Iterator<Item = u64>) -> u64 {
+        lengths.fold(self.position, |position, length| self.padded(position).saturating_add(length))
+    }
+
+    fn padded(&self, position: u64) -> u64 {
+        match self.alignment {
+            0 => position,
+            alignment => position.next_multiple_of(alignment as u64),
+        }
+    }
+
+    // writes zeroes up to the next multiple of the alignment
+    pub fn pad<A: Write>(&mut self, archive: &mut A) -> Result<(), CreateError<A::Error>> {
+        if self.alignment == 0 {
+            return Ok(());
+        }
+        let padding = self.padded(self.position) - self.position;
+        let zeroes = [0u8; 512];
+        let mut remaining = padding;
+        while remaining > 0 {
+            let chunk_length = remaining.min(zeroes.len() as u64) as usize;
+            archive.write_all(&zeroes[..chunk_length]).map_err(CreateError::AddPadding)?;
+            remaining -= chunk_length as u64;
+        }
+        self.position += padding;
+        Ok(())
+    }
+
+    // writes a block, unless an identical one has already been written, and returns where it is
+    pub fn write_block<A: Write>(
+        &mut self,
+        archive: &mut A,
+        bytes: &[u8],
+        map_err: impl FnOnce(A::Error) -> CreateError<A::Error>,
+    ) -> Result<u64, CreateError<A::Error>> {
+        let hash: Option<[u8; 32]> = self.written.as_ref().map(|_| Sha256::digest(bytes).into());
+        if let (Some(written), Some(hash)) = (&self.written, &hash) {
+            if let Some(&offset) = written.get(hash) {
+                return Ok(offset);
+            }
+        }
+
+        self.pad(archive)?;
+        let offset = self.position;
+        archive.write_all(bytes).map_err(map_err)?;
+        self.position += bytes.len() as u64;
+
+        if let (Some(written), Some(hash)) = (&mut self.written, hash) {
+            written.insert(hash, offset);
+        }
+        Ok(offset)
+    }
+}
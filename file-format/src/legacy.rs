@@ -0,0 +1,223 @@
+// decoders for tables written by older versions of the format
+
+use crate::*;
+
+use ::bincode::error::DecodeError;
+
+// versions 0 and 1: no checksums
+#[derive(Decode)]
+struct EntryV1 {
+    offset: u64,
+    length: u64,
+}
+
+impl From<EntryV1> for Entry {
+    fn from(entry: EntryV1) -> Self {
+        Entry {
+            offset: entry.offset,
+            length: entry.length,
+            checksum: None,
+            compression: Compression::None,
+            decompressed_length: entry.length,
+            metadata: Metadata::default(),
+            thumbnail: None,
+            volume: 0,
+        }
+    }
+}
+
+// there's no table length yet either, the table just runs to the end of the archive. giving the header one means
+// it gets read and checked like any other table
+pub(crate) fn set_table_length_v1(header: &mut Header, archive_length: u64) {
+    if header.version < 2 {
+        header.table_length = archive_length.saturating_sub(header.table_address);
+    }
+}
+
+pub(crate) fn decode_table_v1(table_bytes: &[u8]) -> Result<BTreeMap<String, Entry>, DecodeError> {
+    let (table, _): (BTreeMap<String, EntryV1>, _) = bincode::decode_from_slice(table_bytes, BINCODE_CONFIG)?;
+    Ok(table.into_iter().map(|(filename, entry)| (filename, entry.into())).collect())
+}
+
+// version 2: no compression
+#[derive(Decode)]
+struct EntryV2 {
+    offset: u64,
+    length: u64,
+    checksum: Option<u32>,
+}
+
+impl From<EntryV2> for Entry {
+    fn from(entry: EntryV2) -> Self {
+        Entry {
+            offset: entry.offset,
+            length: entry.length,
+            checksum: entry.checksum,
+            compression: Compression::None,
+            decompressed_length: entry.length,
+            metadata: Metadata::default(),
+            thumbnail: None,
+            volume: 0,
+        }
+    }
+}
+
+pub(crate) fn decode_table_v2(table_bytes: &[u8]) -> Result<BTreeMap<String, Entry>, DecodeError> {
+    let (table, _): (BTreeMap<String, EntryV2>, _) = bincode::decode_from_slice(table_bytes, BINCODE_CONFIG)?;
+    Ok(table.into_iter().map(|(filename, entry)| (filename, entry.into())).collect())
+}
+
+// version 3: no metadata
+#[derive(Decode)]
+struct EntryV3 {
+    offset: u64,
+    length: u64,
+    checksum: Option<u32>,
+    compression: Compression,
+    decompressed_length: u64,
+}
+
+impl From<EntryV3> for Entry {
+    fn from(entry: EntryV3) -> Self {
+        Entry {
+            offset: entry.offset,
+            length: entry.length,
+            checksum: entry.checksum,
+            compression: entry.compression,
+            decompressed_length: entry.decompressed_length,
+            metadata: Metadata::default(),
+            thumbnail: None,
+            volume: 0,
+        }
+    }
+}
+
+pub(crate) fn decode_table_v3(table_bytes: &[u8]) -> Result<BTreeMap<String, Entry>, DecodeError> {
+    let (table, _): (BTreeMap<String, EntryV3>, _) = bincode::decode_from_slice(table_bytes, BINCODE_CONFIG)?;
+    Ok(table.into_iter().map(|(filename, entry)| (filename, entry.into())).collect())
+}
+
+// version 4: no thumbnails
+#[derive(Decode)]
+struct EntryV4 {
+    offset: u64,
+    length: u64,
+    checksum: Option<u32>,
+    compression: Compression,
+    decompressed_length: u64,
+    metadata: MetadataV7,
+}
+
+impl From<EntryV4> for Entry {
+    fn from(entry: EntryV4) -> Self {
+        Entry {
+            offset: entry.offset,
+            length: entry.length,
+            checksum: entry.checksum,
+            compression: entry.compression,
+            decompressed_length: entry.decompressed_length,
+            metadata: entry.metadata.into(),
+            thumbnail: None,
+            volume: 0,
+        }
+    }
+}
+
+pub(crate) fn decode_table_v4(table_bytes: &[u8]) -> Result<BTreeMap<String, Entry>, DecodeError> {
+    let (table, _): (BTreeMap<String, EntryV4>, _) = bincode::decode_from_slice(table_bytes, BINCODE_CONFIG)?;
+    Ok(table.into_iter().map(|(filename, entry)| (filename, entry.into())).collect())
+}
+
+// versions 5 and 6: no volumes
+#[derive(Decode)]
+struct EntryV6 {
+    offset: u64,
+    length: u64,
+    checksum: Option<u32>,
+    compression: Compression,
+    decompressed_length: u64,
+    metadata: MetadataV7,
+    thumbnail: Option<Thumbnail>,
+}
+
+impl From<EntryV6> for Entry {
+    fn from(entry: EntryV6) -> Self {
+        Entry {
+            offset: entry.offset,
+            length: entry.length,
+            checksum: entry.checksum,
+            compression: entry.compression,
+            decompressed_length: entry.decompressed_length,
+            metadata: entry.metadata.into(),
+            thumbnail: entry.thumbnail,
+            volume: 0,
+        }
+    }
+}
+
+pub(crate) fn decode_table_v6(table_bytes: &[u8]) -> Result<BTreeMap<String, Entry>, DecodeError> {
+    let (table, _): (BTreeMap<String, EntryV6>, _) = bincode::decode_from_slice(table_bytes, BINCODE_CONFIG)?;
+    Ok(table.into_iter().map(|(filename, entry)| (filename, entry.into())).collect())
+}
+
+// version 7: no image levels
+#[derive(Decode)]
+struct EntryV7 {
+    offset: u64,
+    length: u64,
+    checksum: Option<u32>,
+    compression: Compression,
+    decompressed_length: u64,
+    metadata: MetadataV7,
+    thumbnail: Option<Thumbnail>,
+    volume: u16,
+}
+
+impl From<EntryV7> for Entry {
+    fn from(entry: EntryV7) -> Self {
+        Entry {
+            offset: entry.offset,
+            length: entry.length,
+            checksum: entry.checksum,
+            compression: entry.compression,
+            decompressed_length: entry.decompressed_length,
+            metadata: entry.metadata.into(),
+            thumbnail: entry.thumbnail,
+            volume: entry.volume,
+        }
+    }
+}
+
+pub(crate) fn decode_table_v7(table_bytes: &[u8]) -> Result<BTreeMap<String, Entry>, DecodeError> {
+    let (table, _): (BTreeMap<String, EntryV7>, _) = bincode::decode_from_slice(table_bytes, BINCODE_CONFIG)?;
+    Ok(table.into_iter().map(|(filename, entry)| (filename, entry.into())).collect())
+}
+
+// metadata from versions 4 to 7, which is also what indexes from those versions hold
+#[derive(Decode)]
+struct MetadataV7 {
+    media_type: Option<String>,
+    image: Option<ImageInfo>,
+    created: Option<i64>,
+    tags: BTreeMap<String, String>,
+}
+
+impl From<MetadataV7> for Metadata {
+    fn from(metadata: MetadataV7) -> Self {
+        Metadata {
+            media_type: metadata.media_type,
+            image: metadata.image,
+            created: metadata.created,
+            tags: metadata.tags,
+            levels: Vec::new(),
+        }
+    }
+}
+
+pub(crate) fn decode_metadata(version: u64, bytes: &[u8]) -> Result<Metadata, DecodeError> {
+    if version < 8 {
+        let (metadata, _): (MetadataV7, _) = bincode::decode_from_slice(bytes, BINCODE_CONFIG)?;
+        return Ok(metadata.into());
+    }
+    bincode::decode_from_slice(bytes, BINCODE_CONFIG).map(|(metadata, _)| metadata)
+}
@@ -0,0 +1,33 @@
+use crate::{Decode, Encode, Metadata, PATH_SEPARATOR};
+
+use alloc::{format, string::String};
+
+// images can have downscaled copies of themselves, so zooming out doesn't mean reading and shrinking the whole
+// image. each level is an ordinary entry, kept out of the way in LEVELS_DIRECTORY as "<directory>/<image>/<n>",
+// level n being 2^n times smaller. the image's metadata links to its levels by filename, with their sizes so the
+// right one can be picked without opening any
+
+pub const LEVELS_DIRECTORY: &str = ".levels";
+
+#[derive(Debug, PartialEq, Eq, Clone, Decode, Encode)]
+pub struct ImageLevel {
+    pub filename: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+pub fn level_filename(filename: &str, level: u32) -> String {
+    format!("{}{}{}{}{}", LEVELS_DIRECTORY, PATH_SEPARATOR, filename, PATH_SEPARATOR, level)
+}
+
+// levels are only there for the image they belong to, so listings usually leave them out
+pub fn is_level(filename: &str) -> bool {
+    filename.strip_prefix(LEVELS_DIRECTORY).is_some_and(|rest| rest.starts_with(PATH_SEPARATOR))
+}
+
+impl Metadata {
+    // the smallest level at least this wide, or none if the image itself is the closest
+    pub fn level_for_width(&self, width: u32) -> Option<&ImageLevel> {
+        self.levels.iter().rev().find(|level| level.width >= width)
+    }
+}
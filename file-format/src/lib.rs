@@ -3,106 +3,622 @@
 pub mod error;
 pub use error::*;
 
+pub mod header;
+pub use header::*;
+
+pub mod checksum;
+pub use checksum::*;
+
+pub mod compression;
+pub use compression::*;
+
+pub mod directory;
+pub use directory::*;
+
+pub mod metadata;
+pub use metadata::*;
+
+pub mod thumbnail;
+pub use thumbnail::*;
+
+pub mod rgb565;
+pub use rgb565::*;
+
+pub mod tiled;
+pub use tiled::*;
+
+pub mod levels;
+pub use levels::*;
+
+pub mod animation;
+pub use animation::*;
+
+pub mod playlist;
+pub use playlist::*;
+
+mod update;
+
+mod layout;
+use layout::*;
+
+mod dedup;
+
+pub mod index;
+pub use index::*;
+
+pub mod paged;
+pub use paged::*;
+
+pub mod builder;
+pub use builder::*;
+
+pub mod volume;
+pub use volume::*;
+
+pub mod reader;
+pub use reader::*;
+
+#[cfg(feature = "async")]
+pub mod asynch;
+#[cfg(feature = "async")]
+pub use asynch::*;
+
+#[cfg(feature = "std")]
+pub mod std_io;
+#[cfg(feature = "std")]
+pub use std_io::*;
+
 mod bincode;
 use bincode::*;
 
+mod legacy;
+
 extern crate alloc;
 
+#[cfg(feature = "std")]
+extern crate std;
+
 use alloc::vec::Vec;
 use alloc::{collections::btree_map::BTreeMap, vec};
 use alloc::string::String;
 // use bincode::{Decode, Encode};
 use embedded_io::{Read, Seek, SeekFrom, ErrorType, Write};
 
-// 0  .. 20    signature
-// 20 .. 84    table address
-// 84 .. ?     files
-// ?  .. EOF   table
-
-pub const SIGNATURE: &'static str = "Pocket Knife Archive";
+// 0  .. 60    header (see header.rs)
+// 60 .. ?     files, padded to the header's alignment if it has one
+// ?  .. EOF   table, followed by a trailer if the header has the trailer feature flag
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct FileTable(pub BTreeMap<String, Entry>);
 
-#[derive(Debug, PartialEq, Eq, Copy, Clone, Decode, Encode)]
+#[derive(Debug, PartialEq, Eq, Clone, Decode, Encode)]
 pub struct Entry {
     pub offset: u64,
     pub length: u64,
+    // crc32 of the stored bytes, missing in archives from before version 2
+    pub checksum: Option<u32>,
+    pub compression: Compression,
+    pub decompressed_length: u64,
+    pub metadata: Metadata,
+    pub thumbnail: Option<Thumbnail>,
+    // which volume the entry and its thumbnail are in, 0 for the archive itself (see volume.rs)
+    pub volume: u16,
 }
 
 pub trait Archivable<T: ErrorType + ?Sized> {
     fn filename(&self) -> Result<String, T::Error>;
-    fn write_into(&self, archive: &mut T) -> Result<u64, T::Error>;
+    fn write_into<W: Write<Error = T::Error>>(&self, archive: &mut W) -> Result<u64, T::Error>;
+
+    fn compression(&self) -> Compression {
+        Compression::None
+    }
+
+    fn metadata(&self) -> Result<Metadata, T::Error> {
+        Ok(Metadata::default())
+    }
+
+    fn thumbnail(&self) -> Result<Option<ThumbnailImage>, T::Error> {
+        Ok(None)
+    }
+}
+
+// so slices of inputs can be packed as well as inputs given up one at a time
+impl <T: ErrorType + ?Sized, I: Archivable<T>> Archivable<T> for &I {
+    fn filename(&self) -> Result<String, T::Error> {
+        I::filename(self)
+    }
+
+    fn write_into<W: Write<Error = T::Error>>(&self, archive: &mut W) -> Result<u64, T::Error> {
+        I::write_into(self, archive)
+    }
+
+    fn compression(&self) -> Compression {
+        I::compression(self)
+    }
+
+    fn metadata(&self) -> Result<Metadata, T::Error> {
+        I::metadata(self)
+    }
+
+    fn thumbnail(&self) -> Result<Option<ThumbnailImage>, T::Error> {
+        I::thumbnail(self)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
+pub struct CreateOptions {
+    // start every entry and thumbnail at a multiple of this many bytes, so they can be read in whole blocks.
+    // zero packs them back-to-back
+    pub alignment: u32,
+    // store identical entries and thumbnails only once. this needs every input in memory before it's written
+    pub deduplicate: bool,
+    // write the table as an index that can be searched without decoding it first (see index.rs)
+    pub index: bool,
 }
 
 impl FileTable {
+    // inputs are added in order and dropped once they're written, so each one only has to hold onto whatever it
+    // read while it's being added
     pub fn create<A: Write + Seek, I: Archivable<A>>(
         archive: &mut A,
-        input_files: &[I],
+        input_files: impl IntoIterator<Item = I>,
     ) -> Result<FileTable, CreateError<A::Error>> {
-        archive.write(SIGNATURE.as_bytes()).map_err(CreateError::SignatureWrite)?;
-
-        // skip 64-bit table address for now, we don't know it yet
-        archive.seek(SeekFrom::Current(8)).map_err(CreateError::SkipAddress)?;
+        FileTable::create_with_options(archive, input_files, CreateOptions::default())
+    }
 
-        // write the input files, build the table
-        let mut table = BTreeMap::new();
-        for input_file in input_files.iter() {
-            let filename = input_file.filename().map_err(|err| CreateError::InvalidFilename(err))?;
-            if table.contains_key(&filename) {
-                return Err(CreateError::DuplicateFilename(filename));
-            }
-            let offset = archive.stream_position().map_err(CreateError::GetOffset)?;
-            let length = input_file.write_into(archive).map_err(|err| CreateError::AddFile(filename.clone(), err))?;
-            table.insert(filename, Entry { offset, length });
+    pub fn create_with_options<A: Write + Seek, I: Archivable<A>>(
+        archive: &mut A,
+        input_files: impl IntoIterator<Item = I>,
+        options: CreateOptions,
+    ) -> Result<FileTable, CreateError<A::Error>> {
+        let mut builder = ArchiveBuilder::new(archive, options)?;
+        for input_file in input_files {
+            builder.add_entry(&input_file)?;
         }
+        builder.finish()
+    }
 
-        // keep track of where the table is about to get written
-        let table_address: u64 = archive.stream_position().map_err(CreateError::GetFileTableAddress)?;
-
-        // write the table
-        bincode::encode_into_writer(&table, BincodeAdapter(archive), BINCODE_CONFIG).map_err(CreateError::SerializeFileTable)?;
-
-        // write table address back near start of file, after the signature
-        archive.seek(SeekFrom::Start(SIGNATURE.as_bytes().len() as u64)).map_err(CreateError::SeekToFileTableAddress)?;
-        archive.write(&table_address.to_le_bytes()).map_err(CreateError::WriteFileTableAddress)?;
-
-        Ok(FileTable(table))
+    // same as create_with_options, but never seeks (see ArchiveBuilder::new_streaming)
+    pub fn create_streaming<A: Write, I: Archivable<A>>(
+        archive: &mut A,
+        input_files: impl IntoIterator<Item = I>,
+        options: CreateOptions,
+    ) -> Result<FileTable, CreateError<A::Error>> {
+        let mut builder = ArchiveBuilder::new_streaming(archive, options)?;
+        for input_file in input_files {
+            builder.add_entry(&input_file)?;
+        }
+        builder.finish_streaming()
     }
 
     pub fn read<A: Read + Seek>(
         archive: &mut A
     ) -> Result<FileTable, ReadError<A::Error>> {
-        // validate signature
-        let mut signature = [0u8; SIGNATURE.as_bytes().len()];
-        archive.read_exact(&mut signature).map_err(ReadError::NoSignature)?;
-        if signature != *SIGNATURE.as_bytes() {
-            return Err(ReadError::InvalidSignature(signature));
-        }
+        let header = locate_table(archive)?;
+        let table_bytes = read_table_bytes(archive, &header)?;
+        decode_table(&header, &table_bytes)
+    }
 
-        // read table address
-        let table_address = {
-            let mut table_address_bytes: [u8; 8] = [0; 8];
-            archive.read_exact(&mut table_address_bytes).map_err(ReadError::ReadFileTableAddress)?;
-            u64::from_le_bytes(table_address_bytes)
-        };
+    pub fn open_file<A: Read + Seek>(
+        &self,
+        archive: &mut A,
+        filename: String,
+    ) -> Result<Vec<u8>, OpenError<A::Error>> {
+        self.open_file_in(core::slice::from_mut(archive), filename)
+    }
 
-        // read table
-        archive.seek(SeekFrom::Start(table_address)).map_err(ReadError::SeekToFileTable)?;
-        let table = bincode::decode_from_reader(BincodeAdapter(archive), BINCODE_CONFIG).map_err(ReadError::DeserializeFileTable)?;
+    pub fn open_entry<'a, A: Read + Seek>(
+        &self,
+        archive: &'a mut A,
+        filename: String,
+    ) -> Result<EntryReader<'a, A>, OpenError<A::Error>> {
+        self.open_entry_in(core::slice::from_mut(archive), filename)
+    }
 
-        Ok(FileTable(table))
+    pub fn open_thumbnail<A: Read + Seek>(
+        &self,
+        archive: &mut A,
+        filename: String,
+    ) -> Result<ThumbnailImage, OpenError<A::Error>> {
+        self.open_thumbnail_in(core::slice::from_mut(archive), filename)
     }
 
-    pub fn open_file<A: Read + Seek>(
+    // checks every entry and thumbnail against its checksum, without holding a whole entry in memory at once
+    pub fn verify<A: Read + Seek>(
         &self,
         archive: &mut A,
+    ) -> Result<(), VerifyError<A::Error>> {
+        self.verify_in(core::slice::from_mut(archive))
+    }
+
+    // the ones below take every volume of the archive (see volume.rs), a single archive is just volume 0
+
+    pub(crate) fn open_file_in<A: Read + Seek>(
+        &self,
+        volumes: &mut [A],
         filename: String,
     ) -> Result<Vec<u8>, OpenError<A::Error>> {
-        let entry = self.0.get(&filename).ok_or(OpenError::NoSuchFile(filename.into()))?;
-        let mut buffer = vec![0u8; entry.length as usize];
-        archive.seek(SeekFrom::Start(entry.offset)).map_err(OpenError::SeekToStart)?;
-        archive.read_exact(&mut buffer).map_err(OpenError::ReadFile)?;
-        Ok(buffer)
+        let entry = self.0.get(&filename).ok_or(OpenError::NoSuchFile(filename.clone()))?;
+        let archive = volume(volumes, &filename, entry.volume)?;
+        read_entry(archive, filename, entry)
+    }
+
+    pub(crate) fn open_entry_in<'a, A: Read + Seek>(
+        &self,
+        volumes: &'a mut [A],
+        filename: String,
+    ) -> Result<EntryReader<'a, A>, OpenError<A::Error>> {
+        let entry = self.0.get(&filename).ok_or(OpenError::NoSuchFile(filename.clone()))?;
+        let archive = volume(volumes, &filename, entry.volume)?;
+        check_decompressed_length(&filename, entry)?;
+        check_in_archive(archive, &filename, entry.offset, entry.length)?;
+        Ok(EntryReader::new(archive, entry.clone()))
+    }
+
+    pub(crate) fn open_thumbnail_in<A: Read + Seek>(
+        &self,
+        volumes: &mut [A],
+        filename: String,
+    ) -> Result<ThumbnailImage, OpenError<A::Error>> {
+        let entry = self.0.get(&filename).ok_or(OpenError::NoSuchFile(filename.clone()))?;
+        let thumbnail = entry.thumbnail.ok_or(OpenError::NoThumbnail(filename.clone()))?;
+        let archive = volume(volumes, &filename, entry.volume)?;
+        check_in_archive(archive, &filename, thumbnail.offset, thumbnail.length())?;
+        let mut buffer = vec![0u8; thumbnail.length() as usize];
+        archive.seek(SeekFrom::Start(thumbnail.offset)).map_err(|err| OpenError::SeekToStart(filename.clone(), err))?;
+        archive.read_exact(&mut buffer).map_err(|err| OpenError::ReadFile(filename.clone(), err))?;
+        let found = checksum(&buffer);
+        if found != thumbnail.checksum {
+            return Err(OpenError::InvalidChecksum { filename, expected: thumbnail.checksum, found });
+        }
+        Ok(ThumbnailImage::from_bytes(thumbnail.width, thumbnail.height, &buffer))
+    }
+
+    pub(crate) fn verify_in<A: Read + Seek>(
+        &self,
+        volumes: &mut [A],
+    ) -> Result<(), VerifyError<A::Error>> {
+        let mut corrupt_entries = Vec::new();
+        for (filename, entry) in self.0.iter() {
+            let archive = volumes.get_mut(entry.volume as usize)
+                .ok_or(VerifyError::MissingVolume(filename.clone(), entry.volume))?;
+            let entry_corrupt = match entry.checksum {
+                Some(expected) => read_checksum(archive, filename, entry.offset, entry.length)? != expected,
+                None => false,
+            };
+            let thumbnail_corrupt = match entry.thumbnail {
+                Some(thumbnail) => read_checksum(archive, filename, thumbnail.offset, thumbnail.length())? != thumbnail.checksum,
+                None => false,
+            };
+            if entry_corrupt || thumbnail_corrupt {
+                corrupt_entries.push(filename.clone());
+            }
+        }
+        if corrupt_entries.is_empty() {
+            Ok(())
+        } else {
+            Err(VerifyError::CorruptEntries(corrupt_entries))
+        }
+    }
+}
+
+pub(crate) fn add_entry<A: Write, I: Archivable<A>>(
+    archive: &mut A,
+    layout: &mut Layout,
+    table: &mut BTreeMap<String, Entry>,
+    input_file: &I,
+) -> Result<(), CreateError<A::Error>> {
+    let filename = input_file.filename().map_err(CreateError::InvalidFilename)?;
+    check_new_path(table, &filename)?;
+    let entry = write_entry(archive, layout, input_file, &filename)?;
+    table.insert(filename, entry);
+    Ok(())
+}
+
+pub(crate) fn validate_options<E>(options: &CreateOptions) -> Result<(), CreateError<E>> {
+    if options.alignment != 0 && !options.alignment.is_power_of_two() {
+        return Err(CreateError::InvalidAlignment(options.alignment));
+    }
+    Ok(())
+}
+
+pub(crate) fn check_new_path<E>(table: &BTreeMap<String, Entry>, filename: &str) -> Result<(), CreateError<E>> {
+    if table.contains_key(filename) {
+        return Err(CreateError::DuplicateFilename(filename.into()));
+    }
+    validate_path(filename)?;
+    check_path_conflicts(table, filename)
+}
+
+// reads the header (and trailer), and leaves the archive at the start of the table once it's known to be sensible.
+// everything a table can point to in the archive itself has to end before the length found here, so a backend
+// that can't address a whole archive only has to fail its seeks past what it can reach, instead of wrapping, for
+// the archive to be rejected here
+pub(crate) fn locate_table<A: Read + Seek>(archive: &mut A) -> Result<Header, ReadError<A::Error>> {
+    let mut header = Header::read(archive)?;
+    if header.has_trailer() {
+        header.read_trailer(archive)?;
+    }
+
+    // make sure the table is actually inside the archive before reading any of it
+    let archive_length = archive.seek(SeekFrom::End(0)).map_err(ReadError::GetArchiveLength)?;
+    legacy::set_table_length_v1(&mut header, archive_length);
+    check_table_bounds(&header, archive_length)?;
+
+    archive.seek(SeekFrom::Start(header.table_address)).map_err(ReadError::SeekToFileTable)?;
+    Ok(header)
+}
+
+pub(crate) fn read_table_bytes<A: Read>(archive: &mut A, header: &Header) -> Result<Vec<u8>, ReadError<A::Error>> {
+    let mut table_bytes = vec![0u8; header.table_length as usize];
+    archive.read_exact(&mut table_bytes).map_err(ReadError::ReadFileTable)?;
+    // tables before version 2 don't have a checksum
+    if header.version >= 2 {
+        check_table_checksum(header, &table_bytes)?;
+    }
+    Ok(table_bytes)
+}
+
+pub(crate) fn check_table_bounds<E>(header: &Header, mut archive_length: u64) -> Result<(), ReadError<E>> {
+    if header.has_trailer() {
+        archive_length = archive_length.saturating_sub(TRAILER_LENGTH);
+    }
+    let table_end = header.table_address.checked_add(header.table_length);
+    if header.table_address < header.length() || table_end.is_none_or(|table_end| table_end > archive_length) {
+        return Err(ReadError::TableOutOfBounds { address: header.table_address, length: header.table_length });
+    }
+    if header.table_length > MAX_TABLE_LENGTH {
+        return Err(ReadError::TableTooLarge(header.table_length));
+    }
+    Ok(())
+}
+
+pub(crate) fn check_table_checksum<E>(header: &Header, table_bytes: &[u8]) -> Result<(), ReadError<E>> {
+    let found = checksum(table_bytes);
+    if found != header.table_checksum {
+        return Err(ReadError::InvalidTableChecksum { expected: header.table_checksum, found });
+    }
+    Ok(())
+}
+
+pub(crate) fn decode_table<E>(header: &Header, table_bytes: &[u8]) -> Result<FileTable, ReadError<E>> {
+    if header.has_index() {
+        return FileTableView::new(header, table_bytes)?.to_table();
+    }
+    let table = match header.version {
+        0 | 1 => legacy::decode_table_v1(table_bytes),
+        2 => legacy::decode_table_v2(table_bytes),
+        3 => legacy::decode_table_v3(table_bytes),
+        4 => legacy::decode_table_v4(table_bytes),
+        5 | 6 => legacy::decode_table_v6(table_bytes),
+        7 => legacy::decode_table_v7(table_bytes),
+        _ => bincode::decode_from_slice(table_bytes, BINCODE_CONFIG).map(|(table, _)| table),
+    }.map_err(ReadError::DeserializeFileTable)?;
+    validate_entries(&table, header)?;
+    Ok(FileTable(table))
+}
+
+// opens an entry that didn't come from a FileTable, like one from a PagedIndex page. the archive has to be the
+// volume the entry is in
+pub fn read_entry<A: Read + Seek>(
+    archive: &mut A,
+    filename: String,
+    entry: &Entry,
+) -> Result<Vec<u8>, OpenError<A::Error>> {
+    check_decompressed_length(&filename, entry)?;
+    check_in_archive(archive, &filename, entry.offset, entry.length)?;
+    let mut buffer = vec![0u8; entry.length as usize];
+    archive.seek(SeekFrom::Start(entry.offset)).map_err(|err| OpenError::SeekToStart(filename.clone(), err))?;
+    archive.read_exact(&mut buffer).map_err(|err| OpenError::ReadFile(filename.clone(), err))?;
+    decode_entry(filename, entry, buffer)
+}
+
+// checks and decompresses an entry's stored bytes
+pub(crate) fn decode_entry<E>(filename: String, entry: &Entry, buffer: Vec<u8>) -> Result<Vec<u8>, OpenError<E>> {
+    if let Some(expected) = entry.checksum {
+        let found = checksum(&buffer);
+        if found != expected {
+            return Err(OpenError::InvalidChecksum { filename, expected, found });
+        }
+    }
+    entry.compression.decompress(buffer, entry.decompressed_length).map_err(|err| OpenError::Decompress(filename, err))
+}
+
+// writes the table at the given position and records where it went in the header, and in the trailer
+// if there is one
+pub(crate) fn write_table<A: Write>(
+    archive: &mut A,
+    table_address: u64,
+    header: &mut Header,
+    table: &BTreeMap<String, Entry>,
+) -> Result<(), CreateError<A::Error>> {
+    // write the table
+    let table_bytes = if header.has_index() {
+        encode_index(table)
+    } else {
+        bincode::encode_to_vec(table, BINCODE_CONFIG)
+    }.map_err(CreateError::SerializeFileTable)?;
+    if table_bytes.len() as u64 > MAX_TABLE_LENGTH {
+        return Err(CreateError::TableTooLarge(table_bytes.len() as u64));
+    }
+    archive.write_all(&table_bytes).map_err(CreateError::WriteFileTable)?;
+
+    header.table_address = table_address;
+    header.table_length = table_bytes.len() as u64;
+    header.table_checksum = checksum(&table_bytes);
+    if header.has_trailer() {
+        header.write_trailer(archive).map_err(CreateError::WriteTrailer)?;
+    }
+
+    Ok(())
+}
+
+// writes the real header back at the start of the file, once write_table has filled it in
+pub(crate) fn rewrite_header<A: Write + Seek>(archive: &mut A, header: &Header) -> Result<(), CreateError<A::Error>> {
+    if header.has_trailer() {
+        return Ok(());
+    }
+    archive.seek(SeekFrom::Start(0)).map_err(CreateError::SeekToHeader)?;
+    header.write(archive).map_err(CreateError::WriteHeader)
+}
+
+// entries and thumbnails all live between the header and the table, so nothing a table says can make a reader
+// allocate more than the archive holds
+pub(crate) fn validate_entries<E>(table: &BTreeMap<String, Entry>, header: &Header) -> Result<(), ReadError<E>> {
+    for (filename, entry) in table.iter() {
+        validate_entry(header, filename, entry)?;
+    }
+    Ok(())
+}
+
+pub(crate) fn validate_entry<E>(header: &Header, filename: &str, entry: &Entry) -> Result<(), ReadError<E>> {
+    // other volumes' lengths aren't known until they're opened, so that part waits for check_in_archive
+    let in_bounds = |offset: u64, length: u64| match entry.volume {
+        0 => offset >= header.length() && offset.checked_add(length).is_some_and(|end| end <= header.table_address),
+        _ => header.has_volumes() && offset >= VOLUME_HEADER_LENGTH && offset.checked_add(length).is_some(),
+    };
+    let thumbnail_in_bounds = entry.thumbnail.is_none_or(|thumbnail| in_bounds(thumbnail.offset, thumbnail.length()));
+    if !in_bounds(entry.offset, entry.length) || !thumbnail_in_bounds {
+        return Err(ReadError::EntryOutOfBounds(filename.into()));
+    }
+    if entry.decompressed_length > entry.compression.max_decompressed_length(entry.length) {
+        return Err(ReadError::InvalidDecompressedLength(filename.into()));
+    }
+    Ok(())
+}
+
+pub(crate) fn volume<'v, A: ErrorType>(volumes: &'v mut [A], filename: &str, volume: u16) -> Result<&'v mut A, OpenError<A::Error>> {
+    volumes.get_mut(volume as usize).ok_or(OpenError::MissingVolume { filename: filename.into(), volume })
+}
+
+// tables don't have to come from FileTable::read, so check again before allocating anything
+fn check_in_archive<A: Seek>(archive: &mut A, filename: &str, offset: u64, length: u64) -> Result<(), OpenError<A::Error>> {
+    let archive_length = archive.seek(SeekFrom::End(0)).map_err(OpenError::GetArchiveLength)?;
+    check_entry_bounds(filename, offset, length, archive_length)
+}
+
+pub(crate) fn check_entry_bounds<E>(filename: &str, offset: u64, length: u64, archive_length: u64) -> Result<(), OpenError<E>> {
+    if offset.checked_add(length).is_none_or(|end| end > archive_length) {
+        return Err(OpenError::EntryOutOfBounds(filename.into()));
+    }
+    Ok(())
+}
+
+// an uncompressed entry can't be longer than what's stored, or reading it would run into whatever comes next
+pub(crate) fn check_decompressed_length<E>(filename: &str, entry: &Entry) -> Result<(), OpenError<E>> {
+    if entry.decompressed_length > entry.compression.max_decompressed_length(entry.length) {
+        return Err(OpenError::InvalidDecompressedLength(filename.into()));
+    }
+    Ok(())
+}
+
+fn read_checksum<A: Read + Seek>(
+    archive: &mut A,
+    filename: &str,
+    offset: u64,
+    length: u64,
+) -> Result<u32, VerifyError<A::Error>> {
+    archive.seek(SeekFrom::Start(offset)).map_err(|err| VerifyError::SeekToStart(filename.into(), err))?;
+    let mut buffer = [0u8; 4096];
+    let mut hasher = crc32fast::Hasher::new();
+    let mut remaining = length;
+    while remaining > 0 {
+        let chunk_length = remaining.min(buffer.len() as u64) as usize;
+        let chunk = &mut buffer[..chunk_length];
+        archive.read_exact(chunk).map_err(|err| VerifyError::ReadFile(filename.into(), err))?;
+        hasher.update(chunk);
+        remaining -= chunk.len() as u64;
+    }
+    Ok(hasher.finalize())
+}
+
+fn write_entry<A: Write, I: Archivable<A>>(
+    archive: &mut A,
+    layout: &mut Layout,
+    input_file: &I,
+    filename: &str,
+) -> Result<Entry, CreateError<A::Error>> {
+    if input_file.compression() != Compression::None || layout.deduplicates() {
+        // compressors and deduplication need the whole input up front
+        return BufferedEntry::read::<A, I>(input_file, filename)?.write(archive, layout, filename);
+    }
+
+    let metadata = input_file.metadata().map_err(|err| CreateError::GetMetadata(filename.into(), err))?;
+
+    layout.pad(archive)?;
+    let offset = layout.position;
+    let mut writer = ChecksumWriter::new(archive);
+    let length = input_file.write_into(&mut writer).map_err(|err| CreateError::AddFile(filename.into(), err))?;
+    layout.position += length;
+    let checksum = writer.checksum();
+
+    let thumbnail = input_file.thumbnail().map_err(|err| CreateError::GetThumbnail(filename.into(), err))?;
+    let thumbnail = thumbnail.map(|image| write_thumbnail(archive, layout, &image, filename)).transpose()?;
+
+    Ok(Entry {
+        offset,
+        length,
+        checksum: Some(checksum),
+        compression: Compression::None,
+        decompressed_length: length,
+        metadata,
+        thumbnail,
+        volume: 0,
+    })
+}
+
+fn write_thumbnail<A: Write>(
+    archive: &mut A,
+    layout: &mut Layout,
+    image: &ThumbnailImage,
+    filename: &str,
+) -> Result<Thumbnail, CreateError<A::Error>> {
+    let bytes = image.to_bytes();
+    let offset = layout.write_block(archive, &bytes, |err| CreateError::AddThumbnail(filename.into(), err))?;
+    Ok(Thumbnail { offset, width: image.width, height: image.height, checksum: crate::checksum(&bytes) })
+}
+
+// an entry read into memory and compressed, so its size is known before anything gets written
+pub(crate) struct BufferedEntry {
+    metadata: Metadata,
+    stored: Vec<u8>,
+    compression: Compression,
+    decompressed_length: u64,
+    thumbnail: Option<ThumbnailImage>,
+}
+
+impl BufferedEntry {
+    pub fn read<T: ErrorType, I: Archivable<T>>(input_file: &I, filename: &str) -> Result<BufferedEntry, CreateError<T::Error>> {
+        let metadata = input_file.metadata().map_err(|err| CreateError::GetMetadata(filename.into(), err))?;
+        let mut buffer = BufferWriter::new();
+        input_file.write_into(&mut buffer).map_err(|err| CreateError::AddFile(filename.into(), err))?;
+        let decompressed_length = buffer.0.len() as u64;
+        let (stored, compression) = match input_file.compression().compress(&buffer.0) {
+            Some(compressed) => (compressed, input_file.compression()),
+            None => (buffer.0, Compression::None),
+        };
+        let thumbnail = input_file.thumbnail().map_err(|err| CreateError::GetThumbnail(filename.into(), err))?;
+        Ok(BufferedEntry { metadata, stored, compression, decompressed_length, thumbnail })
+    }
+
+    // the blocks write will ask the layout for, in order
+    pub fn block_lengths(&self) -> impl Iterator<Item = u64> + '_ {
+        let thumbnail_length = self.thumbnail.as_ref().map(|image| image.pixels.len() as u64 * 2);
+        core::iter::once(self.stored.len() as u64).chain(thumbnail_length)
+    }
+
+    pub fn write<A: Write>(self, archive: &mut A, layout: &mut Layout, filename: &str) -> Result<Entry, CreateError<A::Error>> {
+        let offset = layout.write_block(archive, &self.stored, |err| CreateError::AddFile(filename.into(), err))?;
+        let thumbnail = self.thumbnail.map(|image| write_thumbnail(archive, layout, &image, filename)).transpose()?;
+        Ok(Entry {
+            offset,
+            length: self.stored.len() as u64,
+            checksum: Some(crate::checksum(&self.stored)),
+            compression: self.compression,
+            decompressed_length: self.decompressed_length,
+            metadata: self.metadata,
+            thumbnail,
+            volume: 0,
+        })
     }
 }
@@ -0,0 +1,47 @@
+use crate::{Decode, Encode, ImageLevel};
+
+use alloc::{collections::btree_map::BTreeMap, string::String, vec::Vec};
+
+// everything here is optional, so a reader can skip opening the payload whenever it's present
+#[derive(Debug, PartialEq, Eq, Clone, Default, Decode, Encode)]
+pub struct Metadata {
+    // MIME type, e.g. "image/bmp"
+    pub media_type: Option<String>,
+    pub image: Option<ImageInfo>,
+    // seconds since the unix epoch
+    pub created: Option<i64>,
+    pub tags: BTreeMap<String, String>,
+    // smaller copies of an image, each its own entry (see levels.rs), largest first
+    pub levels: Vec<ImageLevel>,
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Decode, Encode)]
+pub struct ImageInfo {
+    pub width: u32,
+    pub height: u32,
+    pub pixel_format: PixelFormat,
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Decode, Encode)]
+pub enum PixelFormat {
+    Indexed1,
+    Indexed4,
+    Indexed8,
+    Rgb555,
+    Rgb565,
+    Rgb888,
+    Xrgb8888,
+}
+
+impl PixelFormat {
+    pub fn bits_per_pixel(self) -> u32 {
+        match self {
+            PixelFormat::Indexed1 => 1,
+            PixelFormat::Indexed4 => 4,
+            PixelFormat::Indexed8 => 8,
+            PixelFormat::Rgb555 | PixelFormat::Rgb565 => 16,
+            PixelFormat::Rgb888 => 24,
+            PixelFormat::Xrgb8888 => 32,
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use crate::*;
+
+// reads an index (see index.rs) a page at a time, for archives with too many entries to decode up front. pages
+// are whole entries, in filename order, looked up by position. the table checksum covers the whole index, so it
+// can't be checked here, but every entry still gets the same bounds checks as in FileTable::read
+
+#[derive(Debug, Copy, Clone)]
+pub struct PagedIndex {
+    header: Header,
+    length: usize,
+    // where the string blob starts in the archive, and how long it is
+    blob_address: u64,
+    blob_length: u64,
+}
+
+impl PagedIndex {
+    // only reads the header and the entry count
+    pub fn open<A: Read + Seek>(archive: &mut A) -> Result<PagedIndex, ReadError<A::Error>> {
+        let header = locate_table(archive)?;
+        if !header.has_index() {
+            return Err(ReadError::NoIndex);
+        }
+        let mut count = [0u8; 4];
+        archive.read_exact(&mut count).map_err(ReadError::ReadFileTable)?;
+        let length = u32::from_le_bytes(count) as usize;
+        let records_length = (length as u64).checked_mul(RECORD_LENGTH as u64)
+            .and_then(|records_length| records_length.checked_add(4))
+            .filter(|&records_length| records_length <= header.table_length)
+            .ok_or(ReadError::InvalidIndex)?;
+        Ok(PagedIndex {
+            header,
+            length,
+            blob_address: header.table_address + records_length,
+            blob_length: header.table_length - records_length,
+        })
+    }
+
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    pub fn len(&self) -> usize {
+        self.length
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    // up to count entries starting at a position, fewer at the end of the index
+    pub fn read_page<A: Read + Seek>(
+        &self,
+        archive: &mut A,
+        start: usize,
+        count: usize,
+    ) -> Result<Vec<(String, Entry)>, ReadError<A::Error>> {
+        let end = start.saturating_add(count).min(self.length);
+        if start >= end {
+            return Ok(Vec::new());
+        }
+
+        let records_address = self.header.table_address + 4 + start as u64 * RECORD_LENGTH as u64;
+        let mut records = vec![0u8; (end - start) * RECORD_LENGTH];
+        archive.seek(SeekFrom::Start(records_address)).map_err(ReadError::SeekToFileTable)?;
+        archive.read_exact(&mut records).map_err(ReadError::ReadFileTable)?;
+        let records: Vec<EntryView> = records.chunks_exact(RECORD_LENGTH)
+            .map(|record| EntryView::without_blob(record, self.header.version))
+            .collect();
+
+        // filenames and metadata are both stored in entry order, so a page's worth of each is one read
+        let filenames = self.read_blob_range(archive, records.iter().map(EntryView::filename_range))?;
+        let metadata = self.read_blob_range(archive, records.iter().map(EntryView::metadata_range))?;
+
+        let mut page = Vec::with_capacity(records.len());
+        for record in records.iter() {
+            let filename = filenames.get(record.filename_range())
+                .and_then(|filename| core::str::from_utf8(filename).ok())
+                .ok_or(ReadError::InvalidIndex)?;
+            let metadata = metadata.get(record.metadata_range()).ok_or(ReadError::InvalidIndex)?;
+            let metadata = legacy::decode_metadata(self.header.version, metadata).map_err(ReadError::DeserializeFileTable)?;
+            let entry = Entry { metadata, ..record.entry_without_metadata() };
+            validate_entry(&self.header, filename, &entry)?;
+            page.push((filename.into(), entry));
+        }
+        Ok(page)
+    }
+
+    // where a filename is in filename order, or where it would go if it isn't in the index. only the records and
+    // filenames the search passes through get read
+    pub fn position<A: Read + Seek>(&self, archive: &mut A, filename: &str) -> Result<usize, ReadError<A::Error>> {
+        let mut low = 0;
+        let mut high = self.length;
+        while low < high {
+            let middle = low + (high - low) / 2;
+            if self.read_filename(archive, middle)?.as_slice() < filename.as_bytes() {
+                low = middle + 1;
+            } else {
+                high = middle;
+            }
+        }
+        Ok(low)
+    }
+
+    pub fn find<A: Read + Seek>(&self, archive: &mut A, filename: &str) -> Result<Option<Entry>, ReadError<A::Error>> {
+        let position = self.position(archive, filename)?;
+        let mut page = self.read_page(archive, position, 1)?;
+        Ok(page.pop().filter(|(found, _)| found == filename).map(|(_, entry)| entry))
+    }
+
+    fn read_filename<A: Read + Seek>(&self, archive: &mut A, position: usize) -> Result<Vec<u8>, ReadError<A::Error>> {
+        let mut record = [0u8; RECORD_LENGTH];
+        let record_address = self.header.table_address + 4 + position as u64 * RECORD_LENGTH as u64;
+        archive.seek(SeekFrom::Start(record_address)).map_err(ReadError::SeekToFileTable)?;
+        archive.read_exact(&mut record).map_err(ReadError::ReadFileTable)?;
+        let filename_range = EntryView::without_blob(&record, self.header.version).filename_range();
+        Ok(self.read_blob_range(archive, core::iter::once(filename_range))?.bytes)
+    }
+
+    // reads the part of the blob covering all the ranges, and moves them to be relative to what was read
+    fn read_blob_range<A: Read + Seek>(
+        &self,
+        archive: &mut A,
+        ranges: impl Iterator<Item = (u32, u32)> + Clone,
+    ) -> Result<BlobRange, ReadError<A::Error>> {
+        let start = ranges.clone().map(|(offset, _)| offset as u64).min().unwrap_or(0);
+        let end = ranges.map(|(offset, length)| offset as u64 + length as u64).max().unwrap_or(0);
+        if end > self.blob_length {
+            return Err(ReadError::InvalidIndex);
+        }
+        let mut bytes = vec![0u8; (end - start) as usize];
+        archive.seek(SeekFrom::Start(self.blob_address + start)).map_err(ReadError::SeekToFileTable)?;
+        archive.read_exact(&mut bytes).map_err(ReadError::ReadFileTable)?;
+        Ok(BlobRange { start, bytes })
+    }
+}
+
+struct BlobRange {
+    start: u64,
+    bytes: Vec<u8>,
+}
+
+impl BlobRange {
+    fn get(&self, (offset, length): (u32, u32)) -> Option<&[u8]> {
+        let start = (offset as u64).checked_sub(self.start)? as usize;
+        self.bytes.get(start..start + length as usize)
+    }
+}
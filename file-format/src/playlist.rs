@@ -0,0 +1,50 @@
+use crate::{Decode, Encode, BINCODE_CONFIG, PATH_SEPARATOR};
+
+use alloc::{format, string::String, vec::Vec};
+use bincode::error::{DecodeError, EncodeError};
+
+// named, ordered collections of entries, for showing files in some other order than by filename (which puts
+// "page10" before "page2"). each playlist is an ordinary entry holding the playlist bincode encoded, kept out of
+// the way in PLAYLISTS_DIRECTORY as "<directory>/<name>". items only link to their entries by filename, so ones
+// whose entry has since been removed or renamed are skipped
+
+pub const PLAYLISTS_DIRECTORY: &str = ".playlists";
+
+pub const PLAYLIST_MEDIA_TYPE: &str = "application/x-pocket-knife-playlist";
+
+#[derive(Debug, PartialEq, Eq, Clone, Default, Decode, Encode)]
+pub struct Playlist {
+    pub title: String,
+    pub items: Vec<PlaylistItem>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Decode, Encode)]
+pub struct PlaylistItem {
+    pub filename: String,
+    // shown instead of the filename
+    pub title: Option<String>,
+}
+
+impl Playlist {
+    pub fn encode(&self) -> Result<Vec<u8>, EncodeError> {
+        bincode::encode_to_vec(self, BINCODE_CONFIG)
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Playlist, DecodeError> {
+        bincode::decode_from_slice(bytes, BINCODE_CONFIG).map(|(playlist, _)| playlist)
+    }
+}
+
+pub fn playlist_filename(name: &str) -> String {
+    format!("{}{}{}", PLAYLISTS_DIRECTORY, PATH_SEPARATOR, name)
+}
+
+// playlists are read through playlist_name, so listings usually leave them out like levels
+pub fn is_playlist(filename: &str) -> bool {
+    playlist_name(filename).is_some()
+}
+
+// the name a playlist's entry was stored under, if it's a playlist
+pub fn playlist_name(filename: &str) -> Option<&str> {
+    filename.strip_prefix(PLAYLISTS_DIRECTORY)?.strip_prefix(PATH_SEPARATOR)
+}
@@ -0,0 +1,84 @@
+use crate::{Compression, Entry, EntryReadError};
+
+use alloc::{vec, vec::Vec};
+use embedded_io::{ErrorType, Read, ReadExactError, Seek, SeekFrom};
+
+// reads one entry's bytes straight from the archive, without buffering the whole entry
+pub struct EntryReader<'a, A> {
+    archive: &'a mut A,
+    entry: Entry,
+    position: u64,
+    // compressed entries can't be read piecewise, so they get decompressed in one go on the first read
+    decompressed: Option<Vec<u8>>,
+}
+
+impl <'a, A: Read + Seek> EntryReader<'a, A> {
+    pub fn new(archive: &'a mut A, entry: Entry) -> Self {
+        EntryReader { archive, entry, position: 0, decompressed: None }
+    }
+
+    pub fn entry(&self) -> &Entry {
+        &self.entry
+    }
+
+    pub fn decompressed_length(&self) -> u64 {
+        self.entry.decompressed_length
+    }
+
+    fn decompress(&mut self) -> Result<Vec<u8>, EntryReadError<A::Error>> {
+        let mut buffer = vec![0u8; self.entry.length as usize];
+        self.archive.seek(SeekFrom::Start(self.entry.offset)).map_err(EntryReadError::Archive)?;
+        self.archive.read_exact(&mut buffer).map_err(|err| match err {
+            ReadExactError::UnexpectedEof => EntryReadError::UnexpectedEof,
+            ReadExactError::Other(err) => EntryReadError::Archive(err),
+        })?;
+        self.entry.compression.decompress(buffer, self.entry.decompressed_length).map_err(EntryReadError::Decompress)
+    }
+}
+
+impl <'a, A: ErrorType> ErrorType for EntryReader<'a, A> {
+    type Error = EntryReadError<A::Error>;
+}
+
+impl <'a, A: Read + Seek> Read for EntryReader<'a, A> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        let remaining = self.decompressed_length().saturating_sub(self.position);
+        if remaining == 0 || buf.is_empty() {
+            return Ok(0);
+        }
+        let length = remaining.min(buf.len() as u64) as usize;
+
+        if self.entry.compression != Compression::None {
+            if self.decompressed.is_none() {
+                self.decompressed = Some(self.decompress()?);
+            }
+            let start = self.position as usize;
+            buf[..length].copy_from_slice(&self.decompressed.as_ref().unwrap()[start..start + length]);
+            self.position += length as u64;
+            return Ok(length);
+        }
+
+        // the archive might be shared with other readers, so always seek before reading
+        let position = self.entry.offset.checked_add(self.position).ok_or(EntryReadError::InvalidSeek(SeekFrom::Start(self.position)))?;
+        self.archive.seek(SeekFrom::Start(position)).map_err(EntryReadError::Archive)?;
+        let read = self.archive.read(&mut buf[..length]).map_err(EntryReadError::Archive)?;
+        // the entry isn't over yet, so the archive ending here means it's been cut short
+        if read == 0 {
+            return Err(EntryReadError::UnexpectedEof);
+        }
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+impl <'a, A: Read + Seek> Seek for EntryReader<'a, A> {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, Self::Error> {
+        let (base, offset) = match pos {
+            SeekFrom::Start(position) => (position, 0),
+            SeekFrom::Current(offset) => (self.position, offset),
+            SeekFrom::End(offset) => (self.decompressed_length(), offset),
+        };
+        self.position = base.checked_add_signed(offset).ok_or(EntryReadError::InvalidSeek(pos))?;
+        Ok(self.position)
+    }
+}
@@ -0,0 +1,202 @@
+use crate::{Rgb565DecodeError, DECODE_LIMIT};
+
+use alloc::vec::Vec;
+
+// images stored the way the Pocket's framebuffer wants them, so showing one doesn't need a decoder:
+//
+// 0  .. 4     magic "P565"
+// 4  .. 8     width
+// 8  .. 12    height
+// 12 .. 16    stride, pixels per row including any padding at the end
+// 16 .. 17    encoding: 0 = raw, 1 = rle
+// 17 .. 20    unused
+// 20 .. end   rows, top to bottom, as little-endian rgb565 pixels
+//
+// rle rows are packets, each starting with a little-endian u16. with the top bit set, the next pixel repeats
+// (the rest + 1) times, otherwise (the rest + 1) pixels follow as they are. packets never cross rows, so every
+// row can be decoded on its own
+
+pub const RGB565_MEDIA_TYPE: &str = "image/x-pocket-knife-rgb565";
+
+pub const RGB565_MAGIC: &[u8; 4] = b"P565";
+
+pub const RGB565_HEADER_LENGTH: usize = 20;
+
+const RUN: u16 = 1 << 15;
+const MAX_PACKET_LENGTH: usize = RUN as usize;
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
+pub enum Rgb565Encoding {
+    #[default]
+    Raw,
+    Rle,
+}
+
+// what the header says about an image, so it can be checked before anything is decoded
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Rgb565Header {
+    pub width: u32,
+    pub height: u32,
+    pub stride: u32,
+    pub encoding: Rgb565Encoding,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Rgb565Image {
+    pub width: u32,
+    pub height: u32,
+    pub stride: u32,
+    // stride * height pixels
+    pub pixels: Vec<u16>,
+}
+
+impl Rgb565Image {
+    // an image without any padding
+    pub fn new(width: u32, height: u32, pixels: Vec<u16>) -> Rgb565Image {
+        Rgb565Image { width, height, stride: width, pixels }
+    }
+
+    // just the visible pixels of a row
+    pub fn row(&self, y: u32) -> &[u16] {
+        let start = y as usize * self.stride as usize;
+        &self.pixels[start..start + self.width as usize]
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[u16]> {
+        (0..self.height).map(|y| self.row(y))
+    }
+
+    pub fn encode(&self, encoding: Rgb565Encoding) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(RGB565_HEADER_LENGTH + self.pixels.len() * 2);
+        bytes.extend_from_slice(RGB565_MAGIC);
+        bytes.extend_from_slice(&self.width.to_le_bytes());
+        bytes.extend_from_slice(&self.height.to_le_bytes());
+        bytes.extend_from_slice(&self.stride.to_le_bytes());
+        let encoding_byte = match encoding {
+            Rgb565Encoding::Raw => 0,
+            Rgb565Encoding::Rle => 1,
+        };
+        bytes.extend_from_slice(&[encoding_byte, 0, 0, 0]);
+        match encoding {
+            Rgb565Encoding::Raw => bytes.extend(self.pixels.iter().flat_map(|pixel| pixel.to_le_bytes())),
+            Rgb565Encoding::Rle => {
+                for row in self.pixels.chunks(self.stride.max(1) as usize) {
+                    encode_rle_row(&mut bytes, row);
+                }
+            },
+        }
+        bytes
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Rgb565Image, Rgb565DecodeError> {
+        let Rgb565Header { width, height, stride, encoding } = Rgb565Header::decode(bytes)?;
+        let length = stride as u64 * height as u64;
+        let body = &bytes[RGB565_HEADER_LENGTH..];
+
+        let pixels = match encoding {
+            Rgb565Encoding::Raw => {
+                if length.checked_mul(2) != Some(body.len() as u64) {
+                    return Err(Rgb565DecodeError::WrongLength { expected: length, found: body.len() as u64 / 2 });
+                }
+                body.chunks_exact(2).map(|pixel| u16::from_le_bytes([pixel[0], pixel[1]])).collect()
+            },
+            Rgb565Encoding::Rle => {
+                // every packet takes at least 4 bytes for at most MAX_PACKET_LENGTH pixels, so a header claiming
+                // more than that can't be right, and isn't worth decoding to find out
+                if length > (body.len() / 4) as u64 * MAX_PACKET_LENGTH as u64 {
+                    return Err(Rgb565DecodeError::InvalidRle);
+                }
+                decode_rle(body, stride, height)?
+            },
+        };
+        Ok(Rgb565Image { width, height, stride, pixels })
+    }
+}
+
+impl Rgb565Header {
+    // just the header, from the start of an encoded image
+    pub fn decode(bytes: &[u8]) -> Result<Rgb565Header, Rgb565DecodeError> {
+        let header = bytes.get(..RGB565_HEADER_LENGTH).ok_or(Rgb565DecodeError::NotRgb565)?;
+        if header[0..4] != *RGB565_MAGIC {
+            return Err(Rgb565DecodeError::NotRgb565);
+        }
+        let u32_at = |offset: usize| u32::from_le_bytes(header[offset..offset + 4].try_into().unwrap());
+        let (width, height, stride) = (u32_at(4), u32_at(8), u32_at(12));
+        if stride < width {
+            return Err(Rgb565DecodeError::InvalidStride { width, stride });
+        }
+        // the same cap as decoding a table, however big the header says the image is
+        let decoded_length = (stride as u64 * height as u64).saturating_mul(2);
+        if decoded_length > DECODE_LIMIT as u64 {
+            return Err(Rgb565DecodeError::TooLarge(decoded_length));
+        }
+        let encoding = match header[16] {
+            0 => Rgb565Encoding::Raw,
+            1 => Rgb565Encoding::Rle,
+            encoding => return Err(Rgb565DecodeError::UnknownEncoding(encoding)),
+        };
+        Ok(Rgb565Header { width, height, stride, encoding })
+    }
+}
+
+fn encode_rle_row(bytes: &mut Vec<u8>, row: &[u16]) {
+    let mut start = 0;
+    while start < row.len() {
+        let run = row[start..].iter().take(MAX_PACKET_LENGTH).take_while(|&&pixel| pixel == row[start]).count();
+        // a run of two only saves anything when it isn't breaking up a literal
+        if run > 2 || (run == 2 && start + 2 == row.len()) {
+            bytes.extend_from_slice(&(RUN | (run - 1) as u16).to_le_bytes());
+            bytes.extend_from_slice(&row[start].to_le_bytes());
+            start += run;
+            continue;
+        }
+        // literals go up to the next run worth starting
+        let mut end = start + 1;
+        while end < row.len() && end - start < MAX_PACKET_LENGTH {
+            if end + 2 < row.len() && row[end] == row[end + 1] && row[end] == row[end + 2] {
+                break;
+            }
+            end += 1;
+        }
+        bytes.extend_from_slice(&((end - start - 1) as u16).to_le_bytes());
+        bytes.extend(row[start..end].iter().flat_map(|pixel| pixel.to_le_bytes()));
+        start = end;
+    }
+}
+
+// never produces more pixels than the header says there are, however much the packets claim
+fn decode_rle(mut body: &[u8], stride: u32, height: u32) -> Result<Vec<u16>, Rgb565DecodeError> {
+    let mut pixels = Vec::new();
+    // empty rows don't have any packets, so there's nothing to go through
+    if stride == 0 {
+        return if body.is_empty() { Ok(pixels) } else { Err(Rgb565DecodeError::InvalidRle) };
+    }
+    for _ in 0..height {
+        let row_end = pixels.len() + stride as usize;
+        while pixels.len() < row_end {
+            let (packet, rest) = take_u16(body)?;
+            let count = (packet & !RUN) as usize + 1;
+            if pixels.len() + count > row_end {
+                return Err(Rgb565DecodeError::InvalidRle);
+            }
+            if packet & RUN != 0 {
+                let (pixel, rest) = take_u16(rest)?;
+                pixels.resize(pixels.len() + count, pixel);
+                body = rest;
+            } else {
+                let literal = rest.get(..count * 2).ok_or(Rgb565DecodeError::InvalidRle)?;
+                pixels.extend(literal.chunks_exact(2).map(|pixel| u16::from_le_bytes([pixel[0], pixel[1]])));
+                body = &rest[count * 2..];
+            }
+        }
+    }
+    if !body.is_empty() {
+        return Err(Rgb565DecodeError::InvalidRle);
+    }
+    Ok(pixels)
+}
+
+fn take_u16(bytes: &[u8]) -> Result<(u16, &[u8]), Rgb565DecodeError> {
+    let pixel = bytes.get(..2).ok_or(Rgb565DecodeError::InvalidRle)?;
+    Ok((u16::from_le_bytes([pixel[0], pixel[1]]), &bytes[2..]))
+}
@@ -0,0 +1,112 @@
+use crate::*;
+
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+// lets anything from std::io be used as an archive, or as a reader for ArchiveBuilder::add_reader
+#[derive(Debug, Default, Clone)]
+pub struct StdArchive<T>(pub T);
+
+impl <T> StdArchive<T> {
+    pub fn new(inner: T) -> StdArchive<T> {
+        StdArchive(inner)
+    }
+
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl <T> ErrorType for StdArchive<T> {
+    type Error = io::Error;
+}
+
+impl <T: io::Read> Read for StdArchive<T> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, io::Error> {
+        self.0.read(buf)
+    }
+
+    // running out of archive is its own error in embedded_io, so callers can tell it from a failed read
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), embedded_io::ReadExactError<io::Error>> {
+        self.0.read_exact(buf).map_err(|err| match err.kind() {
+            io::ErrorKind::UnexpectedEof => embedded_io::ReadExactError::UnexpectedEof,
+            _ => embedded_io::ReadExactError::Other(err),
+        })
+    }
+}
+
+impl <T: io::Write> Write for StdArchive<T> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, io::Error> {
+        self.0.write(buf)
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> Result<(), io::Error> {
+        self.0.write_all(buf)
+    }
+
+    fn flush(&mut self) -> Result<(), io::Error> {
+        self.0.flush()
+    }
+}
+
+impl <T: io::Seek> Seek for StdArchive<T> {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, io::Error> {
+        self.0.seek(pos.into())
+    }
+
+    fn rewind(&mut self) -> Result<(), io::Error> {
+        self.0.rewind()
+    }
+
+    fn stream_position(&mut self) -> Result<u64, io::Error> {
+        self.0.stream_position()
+    }
+}
+
+// a file on disk, stored under its own file name with its creation time
+impl <T: ErrorType<Error = io::Error>> Archivable<T> for &Path {
+    fn filename(&self) -> Result<String, io::Error> {
+        self.file_name()
+            .and_then(|filename| filename.to_str())
+            .map(String::from)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, std::format!("invalid filename {:?}", self)))
+    }
+
+    fn write_into<W: Write<Error = io::Error>>(&self, archive: &mut W) -> Result<u64, io::Error> {
+        let mut input_file = File::open(self)?;
+        let mut buffer = [0u8; 4096];
+        let mut length = 0;
+        loop {
+            let read = io::Read::read(&mut input_file, &mut buffer)?;
+            if read == 0 {
+                return Ok(length);
+            }
+            archive.write_all(&buffer[..read])?;
+            length += read as u64;
+        }
+    }
+
+    fn metadata(&self) -> Result<Metadata, io::Error> {
+        let file_metadata = fs::metadata(self)?;
+        let created = file_metadata.created().or_else(|_| file_metadata.modified()).ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map(|duration| duration.as_secs() as i64);
+        Ok(Metadata { created, ..Metadata::default() })
+    }
+}
+
+impl <T: ErrorType<Error = io::Error>> Archivable<T> for PathBuf {
+    fn filename(&self) -> Result<String, io::Error> {
+        Archivable::<T>::filename(&self.as_path())
+    }
+
+    fn write_into<W: Write<Error = io::Error>>(&self, archive: &mut W) -> Result<u64, io::Error> {
+        Archivable::<T>::write_into(&self.as_path(), archive)
+    }
+
+    fn metadata(&self) -> Result<Metadata, io::Error> {
+        Archivable::<T>::metadata(&self.as_path())
+    }
+}
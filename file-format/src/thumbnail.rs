@@ -0,0 +1,36 @@
+use crate::{Decode, Encode};
+
+use alloc::vec::Vec;
+
+// where an entry's thumbnail is stored: raw little-endian rgb565 pixels, row by row, right after the entry's own data
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Decode, Encode)]
+pub struct Thumbnail {
+    pub offset: u64,
+    pub width: u32,
+    pub height: u32,
+    pub checksum: u32,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ThumbnailImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u16>,
+}
+
+impl Thumbnail {
+    pub fn length(&self) -> u64 {
+        self.width as u64 * self.height as u64 * 2
+    }
+}
+
+impl ThumbnailImage {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.pixels.iter().flat_map(|pixel| pixel.to_le_bytes()).collect()
+    }
+
+    pub fn from_bytes(width: u32, height: u32, bytes: &[u8]) -> ThumbnailImage {
+        let pixels = bytes.chunks_exact(2).map(|pixel| u16::from_le_bytes([pixel[0], pixel[1]])).collect();
+        ThumbnailImage { width, height, pixels }
+    }
+}
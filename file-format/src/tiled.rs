@@ -0,0 +1,168 @@
+use crate::{ImageEncodeError, Rgb565Encoding, Rgb565Header, Rgb565Image, TiledImageError};
+
+use alloc::{vec, vec::Vec};
+use core::ops::Range;
+use embedded_io::{Read, Seek, SeekFrom};
+
+// images too big to hold in memory at once, split into tiles that can each be read on their own:
+//
+// 0  .. 4     magic "PTIL"
+// 4  .. 8     width
+// 8  .. 12    height
+// 12 .. 16    tile width
+// 16 .. 20    tile height
+// 20 .. ?     tile directory, row by row: each tile's offset from the start of the image and its length, as u32s
+// ?  .. end   tiles, each a whole rgb565 image without padding (see rgb565.rs). tiles on the right and bottom
+//             edges are cut short to fit the image
+//
+// tiles are found by seeking, so entries holding tiled images have to be stored without compression to be read
+// a tile at a time
+
+pub const TILED_MEDIA_TYPE: &str = "image/x-pocket-knife-tiled-rgb565";
+
+pub const TILED_MAGIC: &[u8; 4] = b"PTIL";
+
+pub const TILED_HEADER_LENGTH: u64 = 20;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TiledImage {
+    pub width: u32,
+    pub height: u32,
+    pub tile_width: u32,
+    pub tile_height: u32,
+    // offset and length of every tile, row by row
+    tiles: Vec<(u32, u32)>,
+}
+
+impl TiledImage {
+    // offsets are u32s, so the whole thing has to fit in 4 GiB
+    pub fn encode(
+        image: &Rgb565Image,
+        tile_width: u32,
+        tile_height: u32,
+        encoding: Rgb565Encoding,
+    ) -> Result<Vec<u8>, ImageEncodeError> {
+        let (tile_width, tile_height) = (tile_width.max(1), tile_height.max(1));
+        let columns = image.width.div_ceil(tile_width);
+        let rows = image.height.div_ceil(tile_height);
+
+        let mut tiles = Vec::new();
+        for row in 0..rows {
+            for column in 0..columns {
+                let (x, y) = (column * tile_width, row * tile_height);
+                let width = tile_width.min(image.width - x);
+                let height = tile_height.min(image.height - y);
+                let pixels = (y..y + height).flat_map(|y| &image.row(y)[x as usize..(x + width) as usize]).copied().collect();
+                tiles.push(Rgb565Image::new(width, height, pixels).encode(encoding));
+            }
+        }
+
+        let directory_end = TILED_HEADER_LENGTH + tiles.len() as u64 * 8;
+        let length = directory_end + tiles.iter().map(|tile| tile.len() as u64).sum::<u64>();
+        if length > u32::MAX as u64 {
+            return Err(ImageEncodeError::TooLarge(length));
+        }
+
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(TILED_MAGIC);
+        for value in [image.width, image.height, tile_width, tile_height] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        let mut offset = directory_end as u32;
+        for tile in tiles.iter() {
+            bytes.extend_from_slice(&offset.to_le_bytes());
+            bytes.extend_from_slice(&(tile.len() as u32).to_le_bytes());
+            offset += tile.len() as u32;
+        }
+        for tile in tiles.iter() {
+            bytes.extend_from_slice(tile);
+        }
+        Ok(bytes)
+    }
+
+    // reads the header and tile directory, from the start of the image
+    pub fn read<R: Read + Seek>(reader: &mut R) -> Result<TiledImage, TiledImageError<R::Error>> {
+        let length = reader.seek(SeekFrom::End(0)).map_err(TiledImageError::Seek)?;
+        reader.seek(SeekFrom::Start(0)).map_err(TiledImageError::Seek)?;
+
+        let mut header = [0u8; TILED_HEADER_LENGTH as usize];
+        reader.read_exact(&mut header).map_err(TiledImageError::Read)?;
+        if header[0..4] != *TILED_MAGIC {
+            return Err(TiledImageError::NotTiled);
+        }
+        let u32_at = |bytes: &[u8], offset: usize| u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+        let [width, height, tile_width, tile_height] = [4, 8, 12, 16].map(|offset| u32_at(&header, offset));
+        if tile_width == 0 || tile_height == 0 {
+            return Err(TiledImageError::InvalidDirectory);
+        }
+
+        // make sure the directory is really there before allocating anything for it
+        let count = width.div_ceil(tile_width) as u64 * height.div_ceil(tile_height) as u64;
+        let directory_length = count.checked_mul(8)
+            .filter(|&directory_length| directory_length <= length.saturating_sub(TILED_HEADER_LENGTH))
+            .and_then(|directory_length| usize::try_from(directory_length).ok())
+            .ok_or(TiledImageError::InvalidDirectory)?;
+        let mut directory = vec![0u8; directory_length];
+        reader.read_exact(&mut directory).map_err(TiledImageError::Read)?;
+        let tiles: Vec<(u32, u32)> = directory.chunks_exact(8).map(|tile| (u32_at(tile, 0), u32_at(tile, 4))).collect();
+        if tiles.iter().any(|&(offset, tile_length)| offset as u64 + tile_length as u64 > length) {
+            return Err(TiledImageError::InvalidDirectory);
+        }
+
+        Ok(TiledImage { width, height, tile_width, tile_height, tiles })
+    }
+
+    pub fn columns(&self) -> u32 {
+        self.width.div_ceil(self.tile_width)
+    }
+
+    pub fn rows(&self) -> u32 {
+        self.height.div_ceil(self.tile_height)
+    }
+
+    // where a tile's top left corner is in the image
+    pub fn tile_origin(&self, column: u32, row: u32) -> (u32, u32) {
+        (column * self.tile_width, row * self.tile_height)
+    }
+
+    // every tile with any part inside a region, as (column, row), row by row
+    pub fn tiles_in(&self, x: u32, y: u32, width: u32, height: u32) -> impl Iterator<Item = (u32, u32)> {
+        let columns = tile_span(x, width, self.width, self.tile_width);
+        let rows = tile_span(y, height, self.height, self.tile_height);
+        rows.flat_map(move |row| columns.clone().map(move |column| (column, row)))
+    }
+
+    pub fn read_tile<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+        column: u32,
+        row: u32,
+    ) -> Result<Rgb565Image, TiledImageError<R::Error>> {
+        if column >= self.columns() || row >= self.rows() {
+            return Err(TiledImageError::NoSuchTile { column, row });
+        }
+        let (offset, length) = self.tiles[(row as u64 * self.columns() as u64 + column as u64) as usize];
+        let mut bytes = vec![0u8; length as usize];
+        reader.seek(SeekFrom::Start(offset as u64)).map_err(TiledImageError::Seek)?;
+        reader.read_exact(&mut bytes).map_err(TiledImageError::Read)?;
+
+        // a tile that isn't the size it should be would draw outside its place, so it isn't worth decoding
+        let header = Rgb565Header::decode(&bytes).map_err(TiledImageError::Decode)?;
+        let (x, y) = self.tile_origin(column, row);
+        let (width, height) = (self.tile_width.min(self.width - x), self.tile_height.min(self.height - y));
+        if header.width != width || header.height != height || header.stride != width {
+            return Err(TiledImageError::InvalidTile { column, row });
+        }
+        Rgb565Image::decode(&bytes).map_err(TiledImageError::Decode)
+    }
+}
+
+// the tiles along one side with any part of start..start + length in them. nothing past the edge of the image
+// is in a tile, and neither is an empty region
+fn tile_span(start: u32, length: u32, size: u32, tile_size: u32) -> Range<u32> {
+    let end = start.saturating_add(length).min(size);
+    if start >= end {
+        return 0..0;
+    }
+    start / tile_size..end.div_ceil(tile_size)
+}
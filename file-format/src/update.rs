@@ -0,0 +1,143 @@
+use crate::*;
+
+// in-place edits never overwrite anything the current header points to: new files and a new table go at the end
+// of the archive, and the header (or a new trailer) gets switched over to them last. whatever the old table and any removed files
+// took up stays behind as dead space until the archive is compacted into a new file.
+
+impl FileTable {
+    pub fn append<A: Read + Write + Seek, I: Archivable<A>>(
+        &mut self,
+        archive: &mut A,
+        input_files: impl IntoIterator<Item = I>,
+    ) -> Result<(), UpdateError<A::Error>> {
+        let mut header = read_current_header(archive)?;
+        let mut table = self.0.clone();
+        let position = archive.seek(SeekFrom::End(0)).map_err(UpdateError::SeekToEnd)?;
+        // the hashes of what's already in the archive aren't stored anywhere, so there's nothing to share with
+        let mut layout = Layout::new(position, header.alignment, false);
+        for input_file in input_files {
+            add_entry(archive, &mut layout, &mut table, &input_file)?;
+        }
+        write_table(archive, layout.position, &mut header, &table)?;
+        rewrite_header(archive, &header)?;
+        self.0 = table;
+        Ok(())
+    }
+
+    pub fn remove<A: Read + Write + Seek>(
+        &mut self,
+        archive: &mut A,
+        filename: String,
+    ) -> Result<(), UpdateError<A::Error>> {
+        let mut table = self.0.clone();
+        if table.remove(&filename).is_none() {
+            return Err(UpdateError::NoSuchFile(filename));
+        }
+        self.replace_table(archive, table)
+    }
+
+    pub fn rename<A: Read + Write + Seek>(
+        &mut self,
+        archive: &mut A,
+        from: String,
+        to: String,
+    ) -> Result<(), UpdateError<A::Error>> {
+        let mut table = self.0.clone();
+        let entry = table.remove(&from).ok_or(UpdateError::NoSuchFile(from))?;
+        check_new_path(&table, &to)?;
+        table.insert(to, entry);
+        self.replace_table(archive, table)
+    }
+
+    // copies only the live parts of the archive into a fresh output, which also upgrades older versions
+    pub fn compact<A: Read + Write + Seek>(
+        &self,
+        archive: &mut A,
+        output: &mut A,
+    ) -> Result<FileTable, UpdateError<A::Error>> {
+        if self.volume_count() > 1 {
+            return Err(UpdateError::HasVolumes);
+        }
+        archive.seek(SeekFrom::Start(0)).map_err(UpdateError::SeekToHeader)?;
+        let source_header = Header::read(archive).map_err(UpdateError::ReadHeader)?;
+
+        // keep the source's alignment, so compacting doesn't undo it. whatever's left of a volume set is all in
+        // the archive itself by now
+        let features = Features { required: source_header.features.required & !REQUIRED_FEATURE_VOLUMES, ..source_header.features };
+        let mut header = Header::new(features, source_header.alignment);
+        header.write(output).map_err(CreateError::WriteHeader)?;
+
+        let mut layout = Layout::new(header.length(), header.alignment, false);
+        // entries that shared their data before still share it afterwards
+        let mut copies = BTreeMap::new();
+        let mut table = BTreeMap::new();
+        for (filename, entry) in self.0.iter() {
+            let mut entry = entry.clone();
+            entry.offset = copy_range(archive, output, &mut layout, &mut copies, filename, entry.offset, entry.length)?;
+            if let Some(thumbnail) = entry.thumbnail.as_mut() {
+                thumbnail.offset = copy_range(archive, output, &mut layout, &mut copies, filename, thumbnail.offset, thumbnail.length())?;
+            }
+            table.insert(filename.clone(), entry);
+        }
+
+        write_table(output, layout.position, &mut header, &table)?;
+        rewrite_header(output, &header)?;
+        Ok(FileTable(table))
+    }
+
+    fn replace_table<A: Read + Write + Seek>(
+        &mut self,
+        archive: &mut A,
+        table: BTreeMap<String, Entry>,
+    ) -> Result<(), UpdateError<A::Error>> {
+        let mut header = read_current_header(archive)?;
+        let position = archive.seek(SeekFrom::End(0)).map_err(UpdateError::SeekToEnd)?;
+        write_table(archive, position, &mut header, &table)?;
+        rewrite_header(archive, &header)?;
+        self.0 = table;
+        Ok(())
+    }
+}
+
+// older versions get upgraded as they're edited: the new table is written in the current format, and the header
+// that switches over to it says so. that only works where the header has the same layout as the current one, and
+// is what switches over, not a trailer
+fn read_current_header<A: Read + Seek>(archive: &mut A) -> Result<Header, UpdateError<A::Error>> {
+    archive.seek(SeekFrom::Start(0)).map_err(UpdateError::SeekToHeader)?;
+    let header = Header::read(archive).map_err(UpdateError::ReadHeader)?;
+    let upgraded = Header { version: VERSION, ..header };
+    if header.version != VERSION && (header.length() != upgraded.length() || header.has_trailer()) {
+        return Err(UpdateError::OutdatedVersion(header.version));
+    }
+    Ok(upgraded)
+}
+
+// returns where the copy starts in the output. copies maps ranges that have already been copied to where they went
+fn copy_range<A: Read + Write + Seek>(
+    archive: &mut A,
+    output: &mut A,
+    layout: &mut Layout,
+    copies: &mut BTreeMap<(u64, u64), u64>,
+    filename: &str,
+    offset: u64,
+    length: u64,
+) -> Result<u64, UpdateError<A::Error>> {
+    if let Some(&new_offset) = copies.get(&(offset, length)) {
+        return Ok(new_offset);
+    }
+    layout.pad(output)?;
+    let new_offset = layout.position;
+    archive.seek(SeekFrom::Start(offset)).map_err(|err| UpdateError::SeekToFile(filename.into(), err))?;
+    let mut buffer = [0u8; 4096];
+    let mut remaining = length;
+    while remaining > 0 {
+        let chunk_length = remaining.min(buffer.len() as u64) as usize;
+        let chunk = &mut buffer[..chunk_length];
+        archive.read_exact(chunk).map_err(|err| UpdateError::ReadFile(filename.into(), err))?;
+        output.write_all(chunk).map_err(|err| CreateError::AddFile(filename.into(), err))?;
+        remaining -= chunk.len() as u64;
+    }
+    layout.position += length;
+    copies.insert((offset, length), new_offset);
+    Ok(new_offset)
+}
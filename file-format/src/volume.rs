@@ -0,0 +1,168 @@
+use crate::*;
+
+// an archive split into volumes keeps only its header and table in the archive itself (volume 0), with the
+// volumes feature flag set. entries and their thumbnails go in numbered volumes 1, 2, ... which are
+// separate files, each starting with its own header:
+//
+// 0  .. 20    signature
+// 20 .. 28    format version
+// 28 .. 30    volume number
+//
+// entries don't span volumes, and an entry's offsets are from the start of the volume it's in. entries only
+// share their data (see dedup.rs) with other entries in the same volume
+
+pub const VOLUME_HEADER_LENGTH: u64 = SIGNATURE.len() as u64 + 8 + 2;
+
+pub fn write_volume_header<A: Write>(archive: &mut A, number: u16) -> Result<(), A::Error> {
+    archive.write_all(SIGNATURE.as_bytes())?;
+    archive.write_all(&VERSION.to_le_bytes())?;
+    archive.write_all(&number.to_le_bytes())?;
+    Ok(())
+}
+
+// makes sure a volume is the one it's supposed to be, and one this reader understands
+pub fn read_volume_header<A: Read>(archive: &mut A, number: u16) -> Result<(), ReadError<A::Error>> {
+    let mut header = [0u8; VOLUME_HEADER_LENGTH as usize];
+    archive.read_exact(&mut header).map_err(|err| ReadError::ReadVolumeHeader(number, err))?;
+    let version = u64::from_le_bytes(header[20..28].try_into().unwrap());
+    let found = u16::from_le_bytes(header[28..30].try_into().unwrap());
+    if header[..20] != *SIGNATURE.as_bytes() || version == 0 || version > VERSION || found != number {
+        return Err(ReadError::InvalidVolume(number));
+    }
+    Ok(())
+}
+
+impl FileTable {
+    // how many volumes the entries are spread over, counting the archive itself
+    pub fn volume_count(&self) -> usize {
+        self.0.values().map(|entry| entry.volume as usize + 1).max().unwrap_or(1)
+    }
+}
+
+// writes an archive as volumes of at most max_volume_size bytes each. create_volume gets called with each volume
+// number as it's needed, and with 0 for the archive itself once everything else is written. entries are buffered
+// so it's known whether they fit before they're written, and one that wouldn't fit in an empty volume is rejected
+pub struct VolumeBuilder<A, F> {
+    create_volume: F,
+    max_volume_size: u64,
+    options: CreateOptions,
+    // the volume entries are currently going into
+    volume: Option<(u16, A, Layout)>,
+    table: BTreeMap<String, Entry>,
+}
+
+impl <A: Write + Seek, F: FnMut(u16) -> Result<A, A::Error>> VolumeBuilder<A, F> {
+    pub fn new(max_volume_size: u64, options: CreateOptions, create_volume: F) -> Result<Self, CreateError<A::Error>> {
+        validate_options(&options)?;
+        Ok(VolumeBuilder { create_volume, max_volume_size, options, volume: None, table: BTreeMap::new() })
+    }
+
+    pub fn table(&self) -> &BTreeMap<String, Entry> {
+        &self.table
+    }
+
+    pub fn add_entry<I: Archivable<A>>(&mut self, input_file: &I) -> Result<(), CreateError<A::Error>> {
+        let filename = input_file.filename().map_err(CreateError::InvalidFilename)?;
+        check_new_path(&self.table, &filename)?;
+        let entry = BufferedEntry::read::<A, I>(input_file, &filename)?;
+
+        let fits = |layout: &Layout| layout.position_after(entry.block_lengths()) <= self.max_volume_size;
+        if !fits(&self.empty_layout()) {
+            return Err(CreateError::EntryTooLarge(filename));
+        }
+        if !self.volume.as_ref().is_some_and(|(_, _, layout)| fits(layout)) {
+            self.next_volume()?;
+        }
+
+        let (number, archive, layout) = self.volume.as_mut().unwrap();
+        let entry = Entry { volume: *number, ..entry.write(archive, layout, &filename)? };
+        self.table.insert(filename, entry);
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<FileTable, CreateError<A::Error>> {
+        // close the last volume before the archive itself gets created
+        self.volume = None;
+        let mut archive = (self.create_volume)(0).map_err(|err| CreateError::CreateVolume(0, err))?;
+        let index = if self.options.index { REQUIRED_FEATURE_INDEX } else { 0 };
+        let features = Features { required: REQUIRED_FEATURE_VOLUMES | index, optional: 0 };
+        let mut header = Header::new(features, self.options.alignment);
+        header.write(&mut archive).map_err(CreateError::WriteHeader)?;
+        write_table(&mut archive, header.length(), &mut header, &self.table)?;
+        rewrite_header(&mut archive, &header)?;
+        Ok(FileTable(self.table))
+    }
+
+    fn empty_layout(&self) -> Layout {
+        Layout::new(VOLUME_HEADER_LENGTH, self.options.alignment, self.options.deduplicate)
+    }
+
+    fn next_volume(&mut self) -> Result<(), CreateError<A::Error>> {
+        let number = match &self.volume {
+            Some((number, _, _)) => number.checked_add(1).ok_or(CreateError::TooManyVolumes)?,
+            None => 1,
+        };
+        // the previous volume is finished, so it can be closed before the next one is opened
+        self.volume = None;
+        let mut archive = (self.create_volume)(number).map_err(|err| CreateError::CreateVolume(number, err))?;
+        write_volume_header(&mut archive, number).map_err(CreateError::WriteHeader)?;
+        self.volume = Some((number, archive, self.empty_layout()));
+        Ok(())
+    }
+}
+
+// every volume of an archive, opened and checked, for reading entries from whichever volume they're in
+pub struct VolumeSet<A> {
+    volumes: Vec<A>,
+}
+
+impl <A: Read + Seek> VolumeSet<A> {
+    // reads the table from the archive itself, then opens as many volumes as it needs. open_volume gets called
+    // with each volume number from 1 up
+    pub fn open(
+        mut archive: A,
+        mut open_volume: impl FnMut(u16) -> Result<A, A::Error>,
+    ) -> Result<(VolumeSet<A>, FileTable), ReadError<A::Error>> {
+        let file_table = FileTable::read(&mut archive)?;
+        let mut volumes = vec![archive];
+        for number in 1..file_table.volume_count() {
+            let number = number as u16;
+            let mut volume = open_volume(number).map_err(|err| ReadError::OpenVolume(number, err))?;
+            read_volume_header(&mut volume, number)?;
+            volumes.push(volume);
+        }
+        Ok((VolumeSet { volumes }, file_table))
+    }
+
+    pub fn len(&self) -> usize {
+        self.volumes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.volumes.is_empty()
+    }
+
+    pub fn volume(&mut self, number: u16) -> Option<&mut A> {
+        self.volumes.get_mut(number as usize)
+    }
+
+    pub fn into_inner(self) -> Vec<A> {
+        self.volumes
+    }
+
+    pub fn open_file(&mut self, file_table: &FileTable, filename: String) -> Result<Vec<u8>, OpenError<A::Error>> {
+        file_table.open_file_in(&mut self.volumes, filename)
+    }
+
+    pub fn open_entry(&mut self, file_table: &FileTable, filename: String) -> Result<EntryReader<'_, A>, OpenError<A::Error>> {
+        file_table.open_entry_in(&mut self.volumes, filename)
+    }
+
+    pub fn open_thumbnail(&mut self, file_table: &FileTable, filename: String) -> Result<ThumbnailImage, OpenError<A::Error>> {
+        file_table.open_thumbnail_in(&mut self.volumes, filename)
+    }
+
+    pub fn verify(&mut self, file_table: &FileTable) -> Result<(), VerifyError<A::Error>> {
+        file_table.verify_in(&mut self.volumes)
+    }
+}
@@ -0,0 +1,51 @@
+mod common;
+use common::*;
+
+use pocket_knife_file_format::{ArchiveBuilder, CreateError, CreateOptions, EntryOptions, FileTable, Header, ThumbnailImage};
+
+const FILES: &[(&str, &[u8])] = &[("a", b"abc"), ("b", b"defgh"), ("c", b"")];
+
+#[test]
+fn entries_and_thumbnails_start_at_multiples_of_the_alignment() {
+    let mut archive = MemoryArchive::default();
+    let options = CreateOptions { alignment: 512, ..CreateOptions::default() };
+    let mut builder = ArchiveBuilder::new(&mut archive, options).unwrap();
+    let thumbnail = ThumbnailImage { width: 1, height: 1, pixels: vec![0xffff] };
+    for (filename, contents) in FILES {
+        let options = EntryOptions { thumbnail: Some(thumbnail.clone()), ..EntryOptions::default() };
+        builder.add_bytes(filename.to_string(), contents, options).unwrap();
+    }
+    let file_table = builder.finish().unwrap();
+
+    for entry in file_table.0.values() {
+        assert_eq!(entry.offset % 512, 0);
+        assert_eq!(entry.thumbnail.unwrap().offset % 512, 0);
+    }
+    archive.position = 0;
+    assert_eq!(Header::read(&mut archive).unwrap().alignment, 512);
+    archive.position = 0;
+    let read = FileTable::read(&mut archive).unwrap();
+    assert_eq!(read, file_table);
+    for (filename, contents) in FILES {
+        assert_eq!(read.open_file(&mut archive, filename.to_string()).unwrap(), *contents);
+    }
+}
+
+#[test]
+fn appended_entries_keep_the_alignment() {
+    let (mut archive, mut file_table) = pack(FILES, CreateOptions { alignment: 64, ..CreateOptions::default() });
+    file_table.append(&mut archive, [("d", b"ijkl")]).unwrap();
+    assert_eq!(file_table.0["d"].offset % 64, 0);
+    archive.position = 0;
+    assert_eq!(FileTable::read(&mut archive).unwrap().open_file(&mut archive, "d".into()).unwrap(), b"ijkl");
+}
+
+#[test]
+fn alignments_have_to_be_powers_of_two() {
+    let mut archive = MemoryArchive::default();
+    let options = CreateOptions { alignment: 48, ..CreateOptions::default() };
+    assert!(matches!(
+        FileTable::create_with_options(&mut archive, FILES, options),
+        Err(CreateError::InvalidAlignment(48)),
+    ));
+}
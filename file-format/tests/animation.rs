@@ -0,0 +1,62 @@
+mod common;
+use common::*;
+
+use pocket_knife_file_format::{
+    Animation, AnimationError, AnimationFrame, Rgb565Encoding, Rgb565Image, ANIMATION_HEADER_LENGTH,
+};
+
+fn frames() -> Vec<AnimationFrame> {
+    [(100, 1), (40, 2), (0, 3)]
+        .map(|(duration, pixel)| AnimationFrame { image: Rgb565Image::new(3, 2, vec![pixel; 6]), duration })
+        .to_vec()
+}
+
+fn encode(encoding: Rgb565Encoding) -> MemoryArchive {
+    MemoryArchive::new(&Animation::encode(3, 2, 5, &frames(), encoding).unwrap())
+}
+
+#[test]
+fn frames_play_back_as_they_were_encoded() {
+    for encoding in [Rgb565Encoding::Raw, Rgb565Encoding::Rle] {
+        let mut reader = encode(encoding);
+        let animation = Animation::read(&mut reader).unwrap();
+        assert_eq!((animation.width, animation.height, animation.plays, animation.len()), (3, 2, 5, 3));
+        for (number, frame) in frames().iter().enumerate() {
+            assert_eq!(animation.duration(number), Some(frame.duration));
+            assert_eq!(animation.read_frame(&mut reader, number).unwrap(), frame.image);
+        }
+        assert_eq!(animation.duration(3), None);
+        assert!(matches!(animation.read_frame(&mut reader, 3), Err(AnimationError::NoSuchFrame(3))));
+    }
+
+    let empty = Animation::encode(3, 2, 0, &[], Rgb565Encoding::Raw).unwrap();
+    assert!(Animation::read(&mut MemoryArchive::new(&empty)).unwrap().is_empty());
+}
+
+#[test]
+fn broken_directories_are_rejected() {
+    let bytes = encode(Rgb565Encoding::Raw).bytes;
+    let directory = ANIMATION_HEADER_LENGTH as usize;
+
+    let mut not_animation = bytes.clone();
+    not_animation[0] = b'X';
+    assert!(matches!(Animation::read(&mut MemoryArchive::new(&not_animation)), Err(AnimationError::NotAnimation)));
+
+    let mut too_many = bytes.clone();
+    too_many[16..20].copy_from_slice(&u32::MAX.to_le_bytes());
+    assert!(matches!(Animation::read(&mut MemoryArchive::new(&too_many)), Err(AnimationError::InvalidDirectory)));
+
+    let mut past_the_end = bytes.clone();
+    past_the_end[directory + 8..directory + 12].copy_from_slice(&u32::MAX.to_le_bytes());
+    assert!(matches!(Animation::read(&mut MemoryArchive::new(&past_the_end)), Err(AnimationError::InvalidDirectory)));
+}
+
+#[test]
+fn frames_have_to_be_the_size_of_the_animation() {
+    let mut frames = frames();
+    frames[1].image = Rgb565Image::new(2, 3, vec![0; 6]);
+    let mut reader = MemoryArchive::new(&Animation::encode(3, 2, 0, &frames, Rgb565Encoding::Raw).unwrap());
+    let animation = Animation::read(&mut reader).unwrap();
+    animation.read_frame(&mut reader, 0).unwrap();
+    assert!(matches!(animation.read_frame(&mut reader, 1), Err(AnimationError::InvalidFrame(1))));
+}
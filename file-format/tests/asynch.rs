@@ -0,0 +1,77 @@
+mod common;
+use common::*;
+
+use pocket_knife_file_format::{CreateOptions, FileTable, OpenError};
+
+use core::future::Future;
+use core::pin::pin;
+use core::task::{Context, Poll, Waker};
+use embedded_io::{ErrorType, SeekFrom};
+
+const FILES: &[(&str, &[u8])] = &[("a.txt", b"hello, world"), ("dir/b.txt", b"goodbye")];
+
+// the in-memory archive again, but async. it never has to wait, so polling once is enough
+struct AsyncArchive(MemoryArchive);
+
+impl ErrorType for AsyncArchive {
+    type Error = <MemoryArchive as ErrorType>::Error;
+}
+
+impl embedded_io_async::Read for AsyncArchive {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        embedded_io::Read::read(&mut self.0, buf)
+    }
+}
+
+impl embedded_io_async::Seek for AsyncArchive {
+    async fn seek(&mut self, pos: SeekFrom) -> Result<u64, Self::Error> {
+        embedded_io::Seek::seek(&mut self.0, pos)
+    }
+}
+
+fn block_on<F: Future>(future: F) -> F::Output {
+    match pin!(future).poll(&mut Context::from_waker(Waker::noop())) {
+        Poll::Ready(output) => output,
+        Poll::Pending => panic!("nothing here should have to wait"),
+    }
+}
+
+// reads the archive both ways, and every file in it
+fn read_both_ways(bytes: &[u8]) -> FileTable {
+    let mut archive = MemoryArchive::new(bytes);
+    let expected = FileTable::read(&mut archive).unwrap();
+    let mut async_archive = AsyncArchive(MemoryArchive::new(bytes));
+    let file_table = block_on(FileTable::read_async(&mut async_archive)).unwrap();
+    assert_eq!(file_table, expected);
+    for filename in file_table.0.keys() {
+        let contents = block_on(file_table.open_file_async(&mut async_archive, filename.clone())).unwrap();
+        assert_eq!(contents, expected.open_file(&mut archive, filename.clone()).unwrap(), "{}", filename);
+    }
+    file_table
+}
+
+#[test]
+fn archives_read_the_same_as_without_async() {
+    let (archive, file_table) = pack(FILES, CreateOptions::default());
+    assert_eq!(read_both_ways(&archive.bytes), file_table);
+
+    let mut async_archive = AsyncArchive(archive);
+    let missing = block_on(file_table.open_file_async(&mut async_archive, "missing".into()));
+    assert!(matches!(missing, Err(OpenError::NoSuchFile(_))));
+}
+
+#[test]
+fn streamed_archives_are_found_through_their_trailer() {
+    let mut output = Vec::new();
+    let file_table = FileTable::create_streaming(&mut output, FILES, CreateOptions::default()).unwrap();
+    assert_eq!(read_both_ways(&output), file_table);
+}
+
+#[test]
+fn old_versions_still_read() {
+    // see legacy.rs for what's in these
+    for fixture in [&include_bytes!("fixtures/v0.pk")[..], include_bytes!("fixtures/v5.pk")] {
+        let file_table = read_both_ways(fixture);
+        assert!(file_table.0.contains_key("a.txt"));
+    }
+}
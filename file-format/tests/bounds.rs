@@ -0,0 +1,125 @@
+mod common;
+use common::*;
+
+use pocket_knife_file_format::{
+    checksum, Compression, CreateOptions, Entry, Features, FileTable, Header, Metadata, OpenError, ReadError,
+    MAX_TABLE_LENGTH,
+};
+
+use std::collections::BTreeMap;
+
+const FILES: &[(&str, &[u8])] = &[("a.txt", b"hello, world"), ("b.txt", b"goodbye")];
+
+fn entry(offset: u64, length: u64) -> Entry {
+    Entry {
+        offset,
+        length,
+        checksum: None,
+        compression: Compression::None,
+        decompressed_length: length,
+        metadata: Metadata::default(),
+        thumbnail: None,
+        volume: 0,
+    }
+}
+
+// an archive holding data_length zeroes and a table that says whatever it's given
+fn archive_with_table(data_length: usize, table: &BTreeMap<String, Entry>) -> MemoryArchive {
+    let mut header = Header::new(Features::default(), 0);
+    // tables are encoded with bincode's standard configuration
+    let table_bytes = bincode::encode_to_vec(table, bincode::config::standard()).unwrap();
+    header.table_address = header.length() + data_length as u64;
+    header.table_length = table_bytes.len() as u64;
+    header.table_checksum = checksum(&table_bytes);
+    let mut archive = MemoryArchive::default();
+    header.write(&mut archive).unwrap();
+    archive.bytes.resize(header.table_address as usize, 0);
+    archive.bytes.extend_from_slice(&table_bytes);
+    archive.position = 0;
+    archive
+}
+
+fn rewrite_header(archive: &mut MemoryArchive, change: impl FnOnce(&mut Header)) {
+    archive.position = 0;
+    let mut header = Header::read(archive).unwrap();
+    change(&mut header);
+    archive.position = 0;
+    header.write(archive).unwrap();
+    archive.position = 0;
+}
+
+#[test]
+fn tables_outside_the_archive_are_rejected() {
+    let (mut archive, _) = pack(FILES, CreateOptions::default());
+    rewrite_header(&mut archive, |header| header.table_address = u64::MAX - 4);
+    assert!(matches!(FileTable::read(&mut archive), Err(ReadError::TableOutOfBounds { .. })));
+
+    let (mut archive, _) = pack(FILES, CreateOptions::default());
+    rewrite_header(&mut archive, |header| header.table_length += 1);
+    assert!(matches!(FileTable::read(&mut archive), Err(ReadError::TableOutOfBounds { .. })));
+
+    // a table can't overlap the header either
+    let (mut archive, _) = pack(FILES, CreateOptions::default());
+    rewrite_header(&mut archive, |header| header.table_address = 4);
+    assert!(matches!(FileTable::read(&mut archive), Err(ReadError::TableOutOfBounds { .. })));
+}
+
+#[test]
+fn tables_too_large_to_read_are_rejected_before_reading_them() {
+    let (mut archive, _) = pack(FILES, CreateOptions::default());
+    let table_length = MAX_TABLE_LENGTH + 1;
+    archive.bytes.resize(archive.bytes.len() + table_length as usize, 0);
+    let table_address = archive.bytes.len() as u64 - table_length;
+    rewrite_header(&mut archive, |header| {
+        header.table_address = table_address;
+        header.table_length = table_length;
+    });
+    assert!(matches!(FileTable::read(&mut archive), Err(ReadError::TableTooLarge(length)) if length == table_length));
+}
+
+#[test]
+fn entries_outside_the_archive_are_rejected() {
+    let header_length = Header::new(Features::default(), 0).length();
+    for (offset, length) in [(header_length, 11), (header_length + 8, 4), (0, 4), (u64::MAX, 2)] {
+        let table = BTreeMap::from([("a".into(), entry(offset, length))]);
+        let mut archive = archive_with_table(10, &table);
+        assert!(
+            matches!(FileTable::read(&mut archive), Err(ReadError::EntryOutOfBounds(filename)) if filename == "a"),
+            "{} bytes at {}", length, offset,
+        );
+    }
+
+    let table = BTreeMap::from([("a".into(), entry(header_length, 10))]);
+    let mut archive = archive_with_table(10, &table);
+    assert!(FileTable::read(&mut archive).is_ok());
+}
+
+#[test]
+fn compressed_entries_claiming_to_be_too_large_are_rejected() {
+    let header_length = Header::new(Features::default(), 0).length();
+    let entry = Entry { compression: Compression::Lz4, decompressed_length: u64::MAX, ..entry(header_length, 10) };
+    let mut archive = archive_with_table(10, &BTreeMap::from([("a".into(), entry)]));
+    assert!(matches!(FileTable::read(&mut archive), Err(ReadError::InvalidDecompressedLength(_))));
+}
+
+#[test]
+fn tables_that_werent_read_are_still_checked_when_opening() {
+    let (mut archive, mut file_table) = pack(FILES, CreateOptions::default());
+    file_table.0.get_mut("a.txt").unwrap().offset = archive.bytes.len() as u64;
+    assert!(matches!(file_table.open_file(&mut archive, "a.txt".into()), Err(OpenError::EntryOutOfBounds(_))));
+    assert!(matches!(file_table.open_entry(&mut archive, "a.txt".into()), Err(OpenError::EntryOutOfBounds(_))));
+
+    // an uncompressed entry claiming to be longer than it is would read into the next one
+    file_table.0.get_mut("b.txt").unwrap().decompressed_length = 100;
+    assert!(matches!(file_table.open_file(&mut archive, "b.txt".into()), Err(OpenError::InvalidDecompressedLength(_))));
+    assert!(matches!(file_table.open_entry(&mut archive, "b.txt".into()), Err(OpenError::InvalidDecompressedLength(_))));
+}
+
+#[test]
+fn truncated_archives_are_errors() {
+    let (archive, _) = pack(FILES, CreateOptions::default());
+    for length in 0..archive.bytes.len() {
+        let mut truncated = MemoryArchive::new(&archive.bytes[..length]);
+        assert!(FileTable::read(&mut truncated).is_err(), "{} bytes", length);
+    }
+}
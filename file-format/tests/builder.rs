@@ -0,0 +1,40 @@
+mod common;
+use common::*;
+
+use pocket_knife_file_format::{ArchiveBuilder, CreateError, CreateOptions, EntryOptions, FileTable};
+
+#[test]
+fn builders_keep_going_after_a_rejected_filename() {
+    let mut archive = MemoryArchive::default();
+    let mut builder = ArchiveBuilder::new(&mut archive, CreateOptions::default()).unwrap();
+    builder.add_bytes("a".into(), b"first", EntryOptions::default()).unwrap();
+    assert!(matches!(
+        builder.add_bytes("a".into(), b"again", EntryOptions::default()),
+        Err(CreateError::DuplicateFilename(_)),
+    ));
+    assert!(matches!(
+        builder.add_bytes("a/b".into(), b"under a file", EntryOptions::default()),
+        Err(CreateError::PathConflict(_)),
+    ));
+    assert!(matches!(
+        builder.add_bytes("c//d".into(), b"empty component", EntryOptions::default()),
+        Err(CreateError::InvalidPath(_)),
+    ));
+    builder.add_reader("c".into(), &mut MemoryArchive::new(b"from a reader"), EntryOptions::default()).unwrap();
+    let file_table = builder.finish().unwrap();
+
+    archive.position = 0;
+    let read = FileTable::read(&mut archive).unwrap();
+    assert_eq!(read, file_table);
+    assert_eq!(read.0.keys().collect::<Vec<_>>(), ["a", "c"]);
+    assert_eq!(read.open_file(&mut archive, "a".into()).unwrap(), b"first");
+    assert_eq!(read.open_file(&mut archive, "c".into()).unwrap(), b"from a reader");
+}
+
+#[test]
+fn only_streaming_builders_finish_streaming() {
+    let mut archive = MemoryArchive::default();
+    let mut builder = ArchiveBuilder::new(&mut archive, CreateOptions::default()).unwrap();
+    builder.add_bytes("a".into(), b"first", EntryOptions::default()).unwrap();
+    assert!(matches!(builder.finish_streaming(), Err(CreateError::NotStreaming)));
+}
@@ -0,0 +1,43 @@
+mod common;
+use common::*;
+
+use pocket_knife_file_format::{checksum, CreateOptions, FileTable, OpenError, ReadError, VerifyError};
+
+const FILES: &[(&str, &[u8])] = &[("a.txt", b"hello, world"), ("b.txt", b"goodbye")];
+
+#[test]
+fn entries_round_trip_with_their_checksums() {
+    let (mut archive, file_table) = pack(FILES, CreateOptions::default());
+    archive.position = 0;
+    assert_eq!(FileTable::read(&mut archive).unwrap(), file_table);
+    for (filename, contents) in FILES {
+        assert_eq!(file_table.0[*filename].checksum, Some(checksum(contents)));
+        assert_eq!(file_table.open_file(&mut archive, filename.to_string()).unwrap(), *contents);
+    }
+    file_table.verify(&mut archive).unwrap();
+}
+
+#[test]
+fn corrupt_entries_are_reported() {
+    let (mut archive, file_table) = pack(FILES, CreateOptions::default());
+    archive.bytes[file_table.0["b.txt"].offset as usize] ^= 1;
+
+    assert!(matches!(
+        file_table.open_file(&mut archive, "b.txt".into()),
+        Err(OpenError::InvalidChecksum { .. }),
+    ));
+    assert_eq!(file_table.open_file(&mut archive, "a.txt".into()).unwrap(), b"hello, world");
+    match file_table.verify(&mut archive) {
+        Err(VerifyError::CorruptEntries(filenames)) => assert_eq!(filenames, ["b.txt"]),
+        result => panic!("expected b.txt to be corrupt, got {:?}", result),
+    }
+}
+
+#[test]
+fn corrupt_tables_are_rejected() {
+    let (mut archive, _) = pack(FILES, CreateOptions::default());
+    // the table is the last thing in the archive
+    *archive.bytes.last_mut().unwrap() ^= 1;
+    archive.position = 0;
+    assert!(matches!(FileTable::read(&mut archive), Err(ReadError::InvalidTableChecksum { .. })));
+}
@@ -0,0 +1,68 @@
+// every test binary compiles this, and none of them use all of it
+#![allow(dead_code)]
+
+use pocket_knife_file_format::{CreateOptions, FileTable};
+
+use embedded_io::{ErrorKind, ErrorType, Read, Seek, SeekFrom, Write};
+
+// an archive in memory, for writing archives and reading them back
+#[derive(Debug, Default, Clone)]
+pub struct MemoryArchive {
+    pub bytes: Vec<u8>,
+    pub position: usize,
+}
+
+impl MemoryArchive {
+    pub fn new(bytes: &[u8]) -> MemoryArchive {
+        MemoryArchive { bytes: bytes.to_vec(), position: 0 }
+    }
+}
+
+impl ErrorType for MemoryArchive {
+    type Error = ErrorKind;
+}
+
+impl Read for MemoryArchive {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, ErrorKind> {
+        let remaining = self.bytes.get(self.position..).unwrap_or_default();
+        let length = remaining.len().min(buf.len());
+        buf[..length].copy_from_slice(&remaining[..length]);
+        self.position += length;
+        Ok(length)
+    }
+}
+
+impl Write for MemoryArchive {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, ErrorKind> {
+        let end = self.position + buf.len();
+        if self.bytes.len() < end {
+            self.bytes.resize(end, 0);
+        }
+        self.bytes[self.position..end].copy_from_slice(buf);
+        self.position = end;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), ErrorKind> {
+        Ok(())
+    }
+}
+
+impl Seek for MemoryArchive {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, ErrorKind> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::Current(offset) => (self.position as u64).checked_add_signed(offset),
+            SeekFrom::End(offset) => (self.bytes.len() as u64).checked_add_signed(offset),
+        };
+        self.position = position.ok_or(ErrorKind::InvalidInput)? as usize;
+        Ok(self.position as u64)
+    }
+}
+
+// packs (filename, contents) pairs into a new archive
+pub fn pack(files: &[(&str, &[u8])], options: CreateOptions) -> (MemoryArchive, FileTable) {
+    let mut archive = MemoryArchive::default();
+    let file_table = FileTable::create_with_options(&mut archive, files, options).unwrap();
+    (archive, file_table)
+}
@@ -0,0 +1,58 @@
+mod common;
+use common::*;
+
+use pocket_knife_file_format::{ArchiveBuilder, CreateOptions, EntryOptions, FileTable, ThumbnailImage};
+
+const FILES: &[(&str, &[u8])] = &[("a", b"same bytes"), ("b", b"other bytes"), ("c", b"same bytes"), ("d", b"")];
+
+const DEDUPLICATE: CreateOptions = CreateOptions { alignment: 0, deduplicate: true, index: false };
+
+#[test]
+fn identical_entries_share_their_data() {
+    let (mut archive, file_table) = pack(FILES, DEDUPLICATE);
+    assert_eq!(file_table.0["a"].offset, file_table.0["c"].offset);
+    assert_ne!(file_table.0["a"].offset, file_table.0["b"].offset);
+    assert_eq!(file_table.shared_entries(), [["a", "c"]]);
+    assert_eq!(file_table.shared_length(), b"same bytes".len() as u64);
+    for (filename, contents) in FILES {
+        assert_eq!(file_table.open_file(&mut archive, filename.to_string()).unwrap(), *contents);
+    }
+
+    let (separate, separate_table) = pack(FILES, CreateOptions::default());
+    assert!(separate_table.shared_entries().is_empty());
+    assert!(archive.bytes.len() < separate.bytes.len());
+}
+
+#[test]
+fn identical_thumbnails_are_shared_between_different_entries() {
+    let mut archive = MemoryArchive::default();
+    let mut builder = ArchiveBuilder::new(&mut archive, DEDUPLICATE).unwrap();
+    let thumbnail = ThumbnailImage { width: 2, height: 1, pixels: vec![1, 2] };
+    for (filename, contents) in [("a", b"one"), ("b", b"two")] {
+        let options = EntryOptions { thumbnail: Some(thumbnail.clone()), ..EntryOptions::default() };
+        builder.add_bytes(filename.into(), contents, options).unwrap();
+    }
+    let file_table = builder.finish().unwrap();
+
+    assert_ne!(file_table.0["a"].offset, file_table.0["b"].offset);
+    assert_eq!(file_table.0["a"].thumbnail.unwrap().offset, file_table.0["b"].thumbnail.unwrap().offset);
+    assert_eq!(file_table.shared_length(), 4);
+    assert_eq!(file_table.open_thumbnail(&mut archive, "b".into()).unwrap(), thumbnail);
+}
+
+#[test]
+fn compacting_keeps_entries_shared() {
+    let (mut archive, mut file_table) = pack(FILES, DEDUPLICATE);
+    file_table.remove(&mut archive, "b".into()).unwrap();
+    let mut output = MemoryArchive::default();
+    let compacted = file_table.compact(&mut archive, &mut output).unwrap();
+
+    assert_eq!(compacted.shared_entries(), [["a", "c"]]);
+    output.position = 0;
+    let read = FileTable::read(&mut output).unwrap();
+    assert_eq!(read, compacted);
+    assert_eq!(read.open_file(&mut output, "c".into()).unwrap(), b"same bytes");
+    // only one copy of the shared bytes, and nothing of the removed entry or the old tables
+    assert!(!output.bytes.windows(b"other bytes".len()).any(|window| window == b"other bytes"));
+    assert_eq!(output.bytes.windows(b"same bytes".len()).filter(|window| *window == b"same bytes").count(), 1);
+}
@@ -0,0 +1,67 @@
+use pocket_knife_file_format::{Child, Compression, Entry, FileTable, Metadata};
+
+use std::collections::BTreeMap;
+
+// children only look at the filenames, so every entry can be the same
+fn entry() -> Entry {
+    Entry {
+        offset: 0,
+        length: 0,
+        checksum: None,
+        compression: Compression::None,
+        decompressed_length: 0,
+        metadata: Metadata::default(),
+        thumbnail: None,
+        volume: 0,
+    }
+}
+
+fn file_table(filenames: &[&str]) -> FileTable {
+    FileTable(filenames.iter().map(|filename| (filename.to_string(), entry())).collect::<BTreeMap<_, _>>())
+}
+
+// "a!" and "a.txt" sort before "a/", and "ab" after it, so they're around the directory "a" but not in it
+fn tree() -> FileTable {
+    file_table(&["a!", "a.txt", "a/b", "a/b/c/d.txt", "a/b/e.txt", "a/f.txt", "ab", "ab/g.txt", "z.txt"])
+}
+
+fn children<'a>(file_table: &'a FileTable, directory: &str) -> Vec<(&'a str, bool)> {
+    file_table.children(directory).into_iter()
+        .map(|(name, child)| (name, child == Child::Directory))
+        .collect()
+}
+
+#[test]
+fn children_are_only_the_next_level_down() {
+    let tree = tree();
+    assert_eq!(children(&tree, ""), [("a!", false), ("a.txt", false), ("a", true), ("ab", false), ("ab", true), ("z.txt", false)]);
+    assert_eq!(children(&tree, "a"), [("b", false), ("b", true), ("f.txt", false)]);
+    assert_eq!(children(&tree, "a/b"), [("c", true), ("e.txt", false)]);
+    assert_eq!(children(&tree, "a/b/c"), [("d.txt", false)]);
+    assert_eq!(children(&tree, "ab"), [("g.txt", false)]);
+    assert!(matches!(tree.children("a")[0], ("b", Child::File(found)) if *found == entry()));
+    // files and things that only start like a directory have no children
+    for path in ["a/f.txt", "a/b/c/d", "z", "missing"] {
+        assert!(children(&tree, path).is_empty(), "{}", path);
+    }
+}
+
+#[test]
+fn directories_are_wherever_a_file_is_inside_one() {
+    let tree = tree();
+    for path in ["", "a", "a/b", "a/b/c", "ab"] {
+        assert!(tree.is_directory(path), "{}", path);
+    }
+    for path in ["a!", "a.txt", "a/f.txt", "a/b/c/d.txt", "a/b/c/d", "z", "z.txt", "missing", "a/"] {
+        assert!(!tree.is_directory(path), "{}", path);
+    }
+}
+
+#[test]
+fn empty_tables_only_have_the_root() {
+    let empty = file_table(&[]);
+    assert!(empty.children("").is_empty());
+    assert!(empty.children("a").is_empty());
+    assert!(empty.is_directory(""));
+    assert!(!empty.is_directory("a"));
+}
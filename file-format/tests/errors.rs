@@ -0,0 +1,109 @@
+mod common;
+use common::*;
+
+use pocket_knife_file_format::{ArchiveBuilder, Compression, CreateOptions, EntryOptions, OpenError};
+
+use core::error::Error;
+use core::fmt::{self, Display, Formatter};
+use embedded_io::{ErrorKind, ErrorType, Read, Seek, SeekFrom};
+
+const FILES: &[(&str, &[u8])] = &[("a.txt", b"hello, world"), ("b.txt", b"goodbye")];
+
+// ErrorKind doesn't implement Error, so there'd be no source() to follow
+#[derive(Debug)]
+struct Broken;
+
+impl Display for Broken {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "the archive is broken")
+    }
+}
+
+impl Error for Broken {}
+
+impl embedded_io::Error for Broken {
+    fn kind(&self) -> ErrorKind {
+        ErrorKind::Other
+    }
+}
+
+// an archive that fails its reads, or its seeks to a position (seeking to the end still works, for its length)
+struct BrokenArchive {
+    archive: MemoryArchive,
+    seeks: bool,
+    reads: bool,
+}
+
+impl ErrorType for BrokenArchive {
+    type Error = Broken;
+}
+
+impl Read for BrokenArchive {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Broken> {
+        if self.reads {
+            return Err(Broken);
+        }
+        self.archive.read(buf).map_err(|_| Broken)
+    }
+}
+
+impl Seek for BrokenArchive {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, Broken> {
+        if self.seeks && matches!(pos, SeekFrom::Start(_)) {
+            return Err(Broken);
+        }
+        self.archive.seek(pos).map_err(|_| Broken)
+    }
+}
+
+fn messages(error: &dyn Error) -> Vec<String> {
+    let mut messages = vec![error.to_string()];
+    let mut source = error.source();
+    while let Some(error) = source {
+        messages.push(error.to_string());
+        source = error.source();
+    }
+    messages
+}
+
+#[test]
+fn failed_reads_name_the_file() {
+    let (archive, file_table) = pack(FILES, CreateOptions::default());
+    let broken = |seeks, reads| BrokenArchive { archive: archive.clone(), seeks, reads };
+
+    let error = file_table.open_file(&mut broken(true, false), "a.txt".into()).unwrap_err();
+    assert!(matches!(&error, OpenError::SeekToStart(filename, Broken) if filename == "a.txt"));
+    assert_eq!(messages(&error), ["couldn't seek to \"a.txt\"", "the archive is broken"]);
+
+    let error = file_table.open_file(&mut broken(false, true), "b.txt".into()).unwrap_err();
+    assert!(matches!(&error, OpenError::ReadFile(filename, _) if filename == "b.txt"));
+    assert_eq!(messages(&error), ["couldn't read \"b.txt\"", "the archive is broken"]);
+}
+
+#[test]
+fn corrupt_entries_name_the_file() {
+    let mut archive = MemoryArchive::default();
+    let mut builder = ArchiveBuilder::new(&mut archive, CreateOptions::default()).unwrap();
+    builder.add_bytes("plain".into(), b"hello, world", EntryOptions::default()).unwrap();
+    let options = EntryOptions { compression: Compression::Lz4, ..EntryOptions::default() };
+    builder.add_bytes("compressed".into(), &[7; 1000], options).unwrap();
+    let mut file_table = builder.finish().unwrap();
+
+    let mut corrupt = BrokenArchive { archive: archive.clone(), seeks: false, reads: false };
+    corrupt.archive.bytes[file_table.0["plain"].offset as usize] ^= 1;
+    let error = file_table.open_file(&mut corrupt, "plain".into()).unwrap_err();
+    assert!(matches!(&error, OpenError::InvalidChecksum { filename, .. } if filename == "plain"));
+    assert!(error.to_string().starts_with("expected \"plain\" to have the checksum "));
+    assert!(error.source().is_none());
+
+    // without a checksum to catch it first, a wrong length only shows up when decompressing
+    let entry = file_table.0.get_mut("compressed").unwrap();
+    entry.checksum = None;
+    entry.decompressed_length -= 1;
+    let mut archive = BrokenArchive { archive, seeks: false, reads: false };
+    let error = file_table.open_file(&mut archive, "compressed".into()).unwrap_err();
+    assert!(matches!(&error, OpenError::Decompress(filename, _) if filename == "compressed"));
+    let messages = messages(&error);
+    assert_eq!(messages[..2], ["couldn't decompress \"compressed\"", "invalid lz4 data"]);
+    assert_eq!(messages.len(), 3);
+}
@@ -0,0 +1,96 @@
+mod common;
+use common::*;
+
+use pocket_knife_file_format::{
+    ArchiveBuilder, Compression, CreateOptions, EntryOptions, FileTable, FileTableView, Metadata, ReadError,
+    ThumbnailImage, RECORD_LENGTH,
+};
+
+use std::collections::BTreeMap;
+
+const INDEX: CreateOptions = CreateOptions { alignment: 0, deduplicate: false, index: true };
+
+fn indexed_archive() -> (MemoryArchive, FileTable) {
+    let mut archive = MemoryArchive::default();
+    let mut builder = ArchiveBuilder::new(&mut archive, INDEX).unwrap();
+    builder.add_bytes("b/two".into(), b"two", EntryOptions::default()).unwrap();
+    let options = EntryOptions {
+        compression: Compression::Lz4,
+        metadata: Metadata { media_type: Some("text/plain".into()), tags: BTreeMap::from([("k".into(), "v".into())]), ..Metadata::default() },
+        thumbnail: Some(ThumbnailImage { width: 1, height: 2, pixels: vec![3, 4] }),
+    };
+    builder.add_bytes("a".into(), &[b'a'; 100], options).unwrap();
+    builder.add_bytes("c".into(), b"", EntryOptions::default()).unwrap();
+    let file_table = builder.finish().unwrap();
+    archive.position = 0;
+    (archive, file_table)
+}
+
+#[test]
+fn indexes_read_back_as_the_same_table() {
+    let (mut archive, file_table) = indexed_archive();
+    assert_eq!(FileTable::read(&mut archive).unwrap(), file_table);
+
+    archive.position = 0;
+    let (header, bytes) = FileTableView::read_index(&mut archive).unwrap();
+    assert!(header.has_index());
+    let view = FileTableView::new::<()>(&header, &bytes).unwrap();
+    assert_eq!(view.len(), 3);
+    assert_eq!(view.iter().map(|entry| entry.filename()).collect::<Vec<_>>(), ["a", "b/two", "c"]);
+    assert_eq!(view.to_table::<()>().unwrap(), file_table);
+}
+
+#[test]
+fn indexes_are_searched_without_decoding_them() {
+    let (mut archive, file_table) = indexed_archive();
+    let (header, bytes) = FileTableView::read_index(&mut archive).unwrap();
+    let view = FileTableView::new::<()>(&header, &bytes).unwrap();
+    for (filename, entry) in file_table.0.iter() {
+        let found = view.find(filename).unwrap();
+        assert_eq!(found.entry().unwrap(), *entry);
+        assert_eq!(view.open_file(&mut archive, filename).unwrap(), file_table.open_file(&mut archive, filename.clone()).unwrap());
+    }
+    for missing in ["", "0", "b", "b/three", "d"] {
+        assert!(view.find(missing).is_none(), "{:?}", missing);
+    }
+}
+
+#[test]
+fn archives_without_an_index_have_no_view() {
+    let (mut archive, _) = pack(&[("a", b"a")], CreateOptions::default());
+    archive.position = 0;
+    assert!(matches!(FileTableView::read_index(&mut archive), Err(ReadError::NoIndex)));
+}
+
+type Breakage = (&'static str, fn(&mut Vec<u8>));
+
+// where a record starts in an index's bytes
+fn record(position: usize) -> usize {
+    4 + position * RECORD_LENGTH
+}
+
+#[test]
+fn broken_indexes_are_rejected_up_front() {
+    let (mut archive, _) = indexed_archive();
+    let (header, bytes) = FileTableView::read_index(&mut archive).unwrap();
+    let breakages: [Breakage; 5] = [
+        ("too many records", |bytes| bytes[0] = 200),
+        ("too short for the count", |bytes| bytes.truncate(3)),
+        ("filename past the blob", |bytes| bytes[record(1)..record(1) + 4].copy_from_slice(&u32::MAX.to_le_bytes())),
+        ("out of order", |bytes| {
+            let first = bytes[record(0)..record(1)].to_vec();
+            bytes.copy_within(record(1)..record(2), record(0));
+            bytes[record(1)..record(2)].copy_from_slice(&first);
+        }),
+        ("unknown compression", |bytes| bytes[record(2) + 36] = 2),
+    ];
+    for (breakage, breaking) in breakages.iter() {
+        let mut broken = bytes.clone();
+        breaking(&mut broken);
+        assert!(matches!(FileTableView::new::<()>(&header, &broken), Err(ReadError::InvalidIndex)), "{}", breakage);
+    }
+
+    let mut broken = bytes.clone();
+    broken[record(2) + 8..record(2) + 16].copy_from_slice(&u64::MAX.to_le_bytes());
+    assert!(matches!(FileTableView::new::<()>(&header, &broken), Err(ReadError::EntryOutOfBounds(_))));
+}
@@ -0,0 +1,92 @@
+mod common;
+use common::*;
+
+use pocket_knife_file_format::{Compression, FileTable};
+
+// archives written by the manager as of each format version, from a.txt (hello, world 20 times), b.txt and
+// dir/b.txt (both "b\n") and c.bmp (4x4 pixels). from version 3 on a.txt is compressed, from 4 on everything is
+// tagged k=v, and from 5 on c.bmp has a 2x2 thumbnail
+const A_TXT: &[u8] = b"hello, world\nhello, world\nhello, world\nhello, world\nhello, world\nhello, world\nhello, world\nhello, world\nhello, world\nhello, world\nhello, world\nhello, world\nhello, world\nhello, world\nhello, world\nhello, world\nhello, world\nhello, world\nhello, world\nhello, world\n";
+
+fn read(fixture: &[u8]) -> (MemoryArchive, FileTable) {
+    let mut archive = MemoryArchive::new(fixture);
+    let file_table = FileTable::read(&mut archive).unwrap();
+    file_table.verify(&mut archive).unwrap();
+    assert_eq!(file_table.open_file(&mut archive, "a.txt".into()).unwrap(), A_TXT);
+    (archive, file_table)
+}
+
+fn filenames(file_table: &FileTable) -> Vec<&str> {
+    file_table.0.keys().map(String::as_str).collect()
+}
+
+#[test]
+fn versions_0_and_1_have_no_checksums() {
+    for fixture in [&include_bytes!("fixtures/v0.pk")[..], include_bytes!("fixtures/v1.pk")] {
+        let (mut archive, file_table) = read(fixture);
+        assert_eq!(filenames(&file_table), ["a.txt", "b.txt"]);
+        assert!(file_table.0.values().all(|entry| entry.checksum.is_none()));
+        assert_eq!(file_table.open_file(&mut archive, "b.txt".into()).unwrap(), b"b\n");
+    }
+}
+
+#[test]
+fn version_2_has_checksums() {
+    let (_, file_table) = read(include_bytes!("fixtures/v2.pk"));
+    assert_eq!(filenames(&file_table), ["a.txt", "b.txt"]);
+    assert!(file_table.0.values().all(|entry| entry.checksum.is_some()));
+}
+
+#[test]
+fn version_3_has_compression_and_directories() {
+    let (mut archive, file_table) = read(include_bytes!("fixtures/v3.pk"));
+    assert_eq!(filenames(&file_table), ["a.txt", "dir/b.txt"]);
+    let a_txt = &file_table.0["a.txt"];
+    assert_eq!(a_txt.compression, Compression::Lz4);
+    assert_eq!(a_txt.decompressed_length, A_TXT.len() as u64);
+    assert!(a_txt.length < a_txt.decompressed_length);
+    assert_eq!(file_table.open_file(&mut archive, "dir/b.txt".into()).unwrap(), b"b\n");
+}
+
+#[test]
+fn version_4_has_metadata() {
+    let (_, file_table) = read(include_bytes!("fixtures/v4.pk"));
+    assert_eq!(filenames(&file_table), ["a.txt", "c.bmp", "dir/b.txt"]);
+    assert!(file_table.0.values().all(|entry| entry.metadata.tags["k"] == "v"));
+    let image = file_table.0["c.bmp"].metadata.image.unwrap();
+    assert_eq!((image.width, image.height), (4, 4));
+}
+
+#[test]
+fn versions_5_and_up_have_thumbnails() {
+    for fixture in [
+        &include_bytes!("fixtures/v5.pk")[..],
+        include_bytes!("fixtures/v6.pk"),
+        include_bytes!("fixtures/v6-index.pk"),
+        include_bytes!("fixtures/v7.pk"),
+        include_bytes!("fixtures/v7-index.pk"),
+    ] {
+        let (mut archive, file_table) = read(fixture);
+        let thumbnail = file_table.0["c.bmp"].thumbnail.unwrap();
+        assert_eq!((thumbnail.width, thumbnail.height), (2, 2));
+        assert_eq!(file_table.open_thumbnail(&mut archive, "c.bmp".into()).unwrap().pixels.len(), 4);
+        assert!(file_table.0.values().all(|entry| entry.volume == 0));
+    }
+}
+
+#[test]
+fn version_6_has_alignment_and_shared_entries() {
+    let (_, file_table) = read(include_bytes!("fixtures/v6.pk"));
+    assert_eq!(filenames(&file_table), ["a.txt", "b.txt", "c.bmp", "dir/b.txt"]);
+    assert!(file_table.0.values().all(|entry| entry.offset % 16 == 0));
+    assert_eq!(file_table.0["b.txt"].offset, file_table.0["dir/b.txt"].offset);
+    assert_eq!(file_table.shared_entries(), [["b.txt", "dir/b.txt"]]);
+}
+
+#[test]
+fn indexes_from_versions_6_and_7_match_their_tables() {
+    let (_, from_6) = read(include_bytes!("fixtures/v6-index.pk"));
+    let (_, from_7) = read(include_bytes!("fixtures/v7-index.pk"));
+    assert_eq!(filenames(&from_6), ["a.txt", "c.bmp", "dir/b.txt"]);
+    assert_eq!(filenames(&from_7), filenames(&from_6));
+}
@@ -0,0 +1,51 @@
+mod common;
+use common::*;
+
+use pocket_knife_file_format::{
+    is_level, level_filename, ArchiveBuilder, CreateOptions, EntryOptions, FileTable, ImageLevel, Metadata,
+};
+
+fn with_levels() -> Metadata {
+    let levels = [(1, 500, 300), (2, 250, 150), (3, 125, 75)]
+        .map(|(level, width, height)| ImageLevel { filename: level_filename("photos/big.bmp", level), width, height });
+    Metadata { levels: levels.to_vec(), ..Metadata::default() }
+}
+
+#[test]
+fn levels_are_kept_out_of_the_way() {
+    assert_eq!(level_filename("photos/big.bmp", 2), ".levels/photos/big.bmp/2");
+    assert!(is_level(&level_filename("big.bmp", 1)));
+    for filename in ["big.bmp", ".levels", ".levelsx/big.bmp", "photos/.levels/big.bmp/1"] {
+        assert!(!is_level(filename), "{:?}", filename);
+    }
+}
+
+#[test]
+fn the_smallest_level_wide_enough_is_picked() {
+    let metadata = with_levels();
+    for (width, level) in [(0, Some(3)), (125, Some(3)), (126, Some(2)), (500, Some(1)), (501, None)] {
+        let found = metadata.level_for_width(width).map(|found| &found.filename);
+        assert_eq!(found, level.map(|level| level_filename("photos/big.bmp", level)).as_ref(), "{}", width);
+    }
+    assert!(Metadata::default().level_for_width(0).is_none());
+}
+
+#[test]
+fn levels_are_stored_alongside_their_image() {
+    let mut archive = MemoryArchive::default();
+    let mut builder = ArchiveBuilder::new(&mut archive, CreateOptions::default()).unwrap();
+    let options = EntryOptions { metadata: with_levels(), ..EntryOptions::default() };
+    builder.add_bytes("photos/big.bmp".into(), b"the image", options).unwrap();
+    for level in with_levels().levels {
+        builder.add_bytes(level.filename, b"smaller", EntryOptions::default()).unwrap();
+    }
+    builder.finish().unwrap();
+
+    archive.position = 0;
+    let file_table = FileTable::read(&mut archive).unwrap();
+    let metadata = &file_table.0["photos/big.bmp"].metadata;
+    assert_eq!(*metadata, with_levels());
+    for level in metadata.levels.iter() {
+        assert_eq!(file_table.open_file(&mut archive, level.filename.clone()).unwrap(), b"smaller");
+    }
+}
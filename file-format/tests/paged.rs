@@ -0,0 +1,56 @@
+mod common;
+use common::*;
+
+use pocket_knife_file_format::{CreateOptions, PagedIndex, ReadError};
+
+const INDEX: CreateOptions = CreateOptions { alignment: 0, deduplicate: false, index: true };
+
+// filenames with a gap between each of them, so there's somewhere for missing ones to go
+fn filenames() -> Vec<String> {
+    (0..40).map(|n| format!("file{:03}", n * 2)).collect()
+}
+
+fn indexed_archive() -> MemoryArchive {
+    let files: Vec<(String, Vec<u8>)> = filenames().into_iter().map(|filename| (filename.clone(), filename.into_bytes())).collect();
+    let mut archive = MemoryArchive::default();
+    pocket_knife_file_format::FileTable::create_with_options(&mut archive, &files, INDEX).unwrap();
+    archive.position = 0;
+    archive
+}
+
+#[test]
+fn pages_cover_the_index_in_order() {
+    let mut archive = indexed_archive();
+    let index = PagedIndex::open(&mut archive).unwrap();
+    assert_eq!(index.len(), 40);
+
+    let mut read = Vec::new();
+    for start in (0..index.len()).step_by(7) {
+        read.extend(index.read_page(&mut archive, start, 7).unwrap().into_iter().map(|(filename, _)| filename));
+    }
+    assert_eq!(read, filenames());
+    assert!(index.read_page(&mut archive, 40, 7).unwrap().is_empty());
+    assert!(index.read_page(&mut archive, usize::MAX, usize::MAX).unwrap().is_empty());
+}
+
+#[test]
+fn positions_are_where_filenames_are_or_would_go() {
+    let mut archive = indexed_archive();
+    let index = PagedIndex::open(&mut archive).unwrap();
+    for (position, filename) in filenames().iter().enumerate() {
+        assert_eq!(index.position(&mut archive, filename).unwrap(), position);
+        let entry = index.find(&mut archive, filename).unwrap().unwrap();
+        assert_eq!(entry.length, filename.len() as u64);
+    }
+    for (missing, position) in [("", 0), ("file", 0), ("file001", 1), ("file041", 21), ("file079", 40), ("z", 40)] {
+        assert_eq!(index.position(&mut archive, missing).unwrap(), position, "{:?}", missing);
+        assert!(index.find(&mut archive, missing).unwrap().is_none());
+    }
+}
+
+#[test]
+fn archives_without_an_index_cant_be_paged() {
+    let (mut archive, _) = pack(&[("a", b"a")], CreateOptions::default());
+    archive.position = 0;
+    assert!(matches!(PagedIndex::open(&mut archive), Err(ReadError::NoIndex)));
+}
@@ -0,0 +1,58 @@
+mod common;
+use common::*;
+
+use pocket_knife_file_format::{
+    is_playlist, playlist_filename, playlist_name, ArchiveBuilder, CreateOptions, EntryOptions, FileTable, Metadata,
+    Playlist, PlaylistItem, PLAYLIST_MEDIA_TYPE,
+};
+
+fn playlist() -> Playlist {
+    let item = |filename: &str, title: Option<&str>| PlaylistItem { filename: filename.into(), title: title.map(Into::into) };
+    Playlist {
+        title: "In order".into(),
+        items: vec![item("page2", None), item("page10", Some("The last page")), item("gone", None)],
+    }
+}
+
+#[test]
+fn playlists_are_kept_out_of_the_way() {
+    assert_eq!(playlist_filename("comics/in order"), ".playlists/comics/in order");
+    assert_eq!(playlist_name(&playlist_filename("comics/in order")), Some("comics/in order"));
+    assert!(is_playlist(".playlists/a"));
+    for filename in ["a", ".playlists", ".playlistsx/a", "comics/.playlists/a"] {
+        assert!(!is_playlist(filename), "{:?}", filename);
+        assert_eq!(playlist_name(filename), None);
+    }
+}
+
+#[test]
+fn playlists_decode_the_way_they_were_encoded() {
+    let bytes = playlist().encode().unwrap();
+    assert_eq!(Playlist::decode(&bytes).unwrap(), playlist());
+    assert_eq!(Playlist::decode(&Playlist::default().encode().unwrap()).unwrap(), Playlist::default());
+    for length in 0..bytes.len() {
+        assert!(Playlist::decode(&bytes[..length]).is_err(), "{}", length);
+    }
+}
+
+#[test]
+fn playlists_are_stored_as_entries_linking_to_others() {
+    let mut archive = MemoryArchive::default();
+    let mut builder = ArchiveBuilder::new(&mut archive, CreateOptions::default()).unwrap();
+    for filename in ["page10", "page2"] {
+        builder.add_bytes(filename.into(), filename.as_bytes(), EntryOptions::default()).unwrap();
+    }
+    let metadata = Metadata { media_type: Some(PLAYLIST_MEDIA_TYPE.into()), ..Metadata::default() };
+    let options = EntryOptions { metadata, ..EntryOptions::default() };
+    builder.add_bytes(playlist_filename("in order"), &playlist().encode().unwrap(), options).unwrap();
+    builder.finish().unwrap();
+
+    archive.position = 0;
+    let file_table = FileTable::read(&mut archive).unwrap();
+    let (filename, entry) = file_table.0.iter().find(|(filename, _)| is_playlist(filename)).unwrap();
+    assert_eq!(entry.metadata.media_type.as_deref(), Some(PLAYLIST_MEDIA_TYPE));
+    let read = Playlist::decode(&file_table.open_file(&mut archive, filename.clone()).unwrap()).unwrap();
+    // the removed entry is still listed, it's whatever reads the playlist that skips it
+    let found: Vec<bool> = read.items.iter().map(|item| file_table.0.contains_key(&item.filename)).collect();
+    assert_eq!(found, [true, true, false]);
+}
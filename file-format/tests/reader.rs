@@ -0,0 +1,88 @@
+mod common;
+use common::*;
+
+use pocket_knife_file_format::{
+    ArchiveBuilder, Compression, CreateOptions, EntryOptions, EntryReadError, EntryReader, FileTable, OpenError,
+};
+
+use embedded_io::{Read, Seek, SeekFrom};
+
+fn contents() -> Vec<u8> {
+    (0..1000u32).flat_map(|n| (n % 7).to_le_bytes()).collect()
+}
+
+// the same contents stored as they are and compressed
+fn archive() -> (MemoryArchive, FileTable) {
+    let mut archive = MemoryArchive::default();
+    let mut builder = ArchiveBuilder::new(&mut archive, CreateOptions::default()).unwrap();
+    builder.add_bytes("plain".into(), &contents(), EntryOptions::default()).unwrap();
+    let options = EntryOptions { compression: Compression::Lz4, ..EntryOptions::default() };
+    builder.add_bytes("compressed".into(), &contents(), options).unwrap();
+    let file_table = builder.finish().unwrap();
+    (archive, file_table)
+}
+
+#[test]
+fn entries_read_in_pieces() {
+    let (mut archive, file_table) = archive();
+    assert_eq!(file_table.0["compressed"].compression, Compression::Lz4);
+    for filename in ["plain", "compressed"] {
+        let mut reader = file_table.open_entry(&mut archive, filename.into()).unwrap();
+        assert_eq!(reader.decompressed_length(), contents().len() as u64);
+        let mut read = Vec::new();
+        let mut buffer = [0u8; 333];
+        loop {
+            let length = reader.read(&mut buffer).unwrap();
+            if length == 0 {
+                break;
+            }
+            read.extend_from_slice(&buffer[..length]);
+        }
+        assert_eq!(read, contents(), "{}", filename);
+    }
+}
+
+#[test]
+fn entries_seek_within_themselves() {
+    let (mut archive, file_table) = archive();
+    for filename in ["plain", "compressed"] {
+        let mut reader = file_table.open_entry(&mut archive, filename.into()).unwrap();
+        let mut buffer = [0u8; 8];
+        reader.seek(SeekFrom::End(-8)).unwrap();
+        reader.read_exact(&mut buffer).unwrap();
+        assert_eq!(buffer, contents()[contents().len() - 8..]);
+        reader.seek(SeekFrom::Start(4)).unwrap();
+        reader.read_exact(&mut buffer).unwrap();
+        assert_eq!(buffer, contents()[4..12]);
+        // nothing past the end of the entry, even though the archive goes on
+        reader.seek(SeekFrom::End(0)).unwrap();
+        assert_eq!(reader.read(&mut buffer).unwrap(), 0);
+        assert!(reader.seek(SeekFrom::Current(-10_000)).is_err());
+    }
+}
+
+#[test]
+fn missing_entries_are_reported() {
+    let (mut archive, file_table) = archive();
+    assert!(matches!(file_table.open_entry(&mut archive, "missing".into()), Err(OpenError::NoSuchFile(_))));
+}
+
+#[test]
+fn archives_cut_short_partway_through_an_entry_are_errors() {
+    let (archive, file_table) = archive();
+    for filename in ["plain", "compressed"] {
+        let entry = file_table.0[filename].clone();
+        let mut truncated = MemoryArchive::new(&archive.bytes[..(entry.offset + entry.length / 2) as usize]);
+        // past open_entry's checks, as if the archive shrank after it was opened
+        let mut reader = EntryReader::new(&mut truncated, entry);
+        let mut buffer = [0u8; 64];
+        let error = loop {
+            match reader.read(&mut buffer) {
+                Ok(0) => panic!("{} read to the end", filename),
+                Ok(_) => {},
+                Err(err) => break err,
+            }
+        };
+        assert!(matches!(error, EntryReadError::UnexpectedEof), "{}", filename);
+    }
+}
@@ -0,0 +1,108 @@
+use pocket_knife_file_format::{
+    Rgb565DecodeError, Rgb565Encoding, Rgb565Header, Rgb565Image, RGB565_HEADER_LENGTH,
+};
+
+// the most a decoded image can take, in bytes
+const DECODE_LIMIT: u32 = 64 * 1024 * 1024;
+
+// runs of every length around the packet limits, single pixels between them, and padding at the end of each row
+fn image() -> Rgb565Image {
+    let mut pixels = Vec::new();
+    for (y, run) in [1, 2, 3, 4, 32768, 32769, 70000].into_iter().enumerate() {
+        let mut row = vec![y as u16; run];
+        row.extend(0..5u16);
+        row.resize(70010, 0xffff);
+        pixels.extend(row);
+    }
+    Rgb565Image { width: 70005, height: 7, stride: 70010, pixels }
+}
+
+#[test]
+fn images_decode_the_way_they_were_encoded() {
+    for image in [image(), Rgb565Image::new(0, 3, Vec::new()), Rgb565Image::new(3, 0, Vec::new())] {
+        for encoding in [Rgb565Encoding::Raw, Rgb565Encoding::Rle] {
+            let bytes = image.encode(encoding);
+            let header = Rgb565Header::decode(&bytes).unwrap();
+            assert_eq!(header, Rgb565Header { width: image.width, height: image.height, stride: image.stride, encoding });
+            assert_eq!(Rgb565Image::decode(&bytes).unwrap(), image, "{:?}", encoding);
+        }
+    }
+    let image = image();
+    assert!(image.encode(Rgb565Encoding::Rle).len() < image.encode(Rgb565Encoding::Raw).len() / 10);
+    assert_eq!(image.rows().map(|row| row.len()).collect::<Vec<_>>(), [70005; 7]);
+}
+
+fn header(width: u32, height: u32, stride: u32, encoding: u8) -> Vec<u8> {
+    let mut bytes = Rgb565Image { width, height, stride, pixels: Vec::new() }.encode(Rgb565Encoding::Raw);
+    bytes[16] = encoding;
+    bytes
+}
+
+#[test]
+fn bad_headers_are_rejected_before_decoding() {
+    assert!(matches!(Rgb565Image::decode(b"P565"), Err(Rgb565DecodeError::NotRgb565)));
+    assert!(matches!(Rgb565Image::decode(&[0; RGB565_HEADER_LENGTH]), Err(Rgb565DecodeError::NotRgb565)));
+    assert!(matches!(Rgb565Image::decode(&header(1, 1, 1, 2)), Err(Rgb565DecodeError::UnknownEncoding(2))));
+    assert!(matches!(
+        Rgb565Image::decode(&header(2, 1, 1, 0)),
+        Err(Rgb565DecodeError::InvalidStride { width: 2, stride: 1 }),
+    ));
+    for encoding in [0, 1] {
+        let too_large = header(u32::MAX, u32::MAX, u32::MAX, encoding);
+        assert!(matches!(Rgb565Image::decode(&too_large), Err(Rgb565DecodeError::TooLarge(_))));
+        let just_too_large = header(DECODE_LIMIT / 2 + 1, 1, DECODE_LIMIT / 2 + 1, encoding);
+        assert!(matches!(Rgb565Image::decode(&just_too_large), Err(Rgb565DecodeError::TooLarge(_))));
+    }
+    // a header claiming far more pixels than the packets after it could hold
+    let mut huge = header(1000, 1000, 1000, 1);
+    huge.extend_from_slice(&[0xff, 0xff, 0, 0]);
+    assert!(matches!(Rgb565Image::decode(&huge), Err(Rgb565DecodeError::InvalidRle)));
+}
+
+#[test]
+fn raw_images_need_exactly_their_pixels() {
+    let bytes = Rgb565Image::new(2, 2, vec![1, 2, 3, 4]).encode(Rgb565Encoding::Raw);
+    for length in [bytes.len() - 2, bytes.len() - 1] {
+        assert!(matches!(
+            Rgb565Image::decode(&bytes[..length]),
+            Err(Rgb565DecodeError::WrongLength { expected: 4, .. }),
+        ));
+    }
+    let mut longer = bytes.clone();
+    longer.extend_from_slice(&[0, 0]);
+    assert!(matches!(Rgb565Image::decode(&longer), Err(Rgb565DecodeError::WrongLength { expected: 4, found: 5 })));
+}
+
+#[test]
+fn packets_stay_within_their_rows_and_the_data() {
+    let rle = |packets: &[u16]| {
+        let mut bytes = header(2, 2, 2, 1);
+        bytes.extend(packets.iter().flat_map(|word| word.to_le_bytes()));
+        bytes
+    };
+    let run = |count: u16| 0x8000 | (count - 1);
+    assert_eq!(Rgb565Image::decode(&rle(&[run(2), 7, 1, 8, 9])).unwrap().pixels, [7, 7, 8, 9]);
+
+    let broken: [&[u16]; 6] = [
+        // a run across both rows
+        &[run(4), 7],
+        // a literal across both rows
+        &[3, 1, 2, 3, 4],
+        // a run without its pixel
+        &[run(2), 7, run(2)],
+        // a literal without all its pixels
+        &[run(2), 7, 1, 8],
+        // not enough rows
+        &[run(2), 7],
+        // something after the last row
+        &[run(2), 7, run(2), 8, 0],
+    ];
+    for packets in broken {
+        assert!(matches!(Rgb565Image::decode(&rle(packets)), Err(Rgb565DecodeError::InvalidRle)), "{:?}", packets);
+    }
+
+    let mut empty_rows = header(0, 2, 0, 1);
+    assert_eq!(Rgb565Image::decode(&empty_rows).unwrap().pixels, []);
+    empty_rows.extend_from_slice(&[0, 0]);
+    assert!(matches!(Rgb565Image::decode(&empty_rows), Err(Rgb565DecodeError::InvalidRle)));
+}
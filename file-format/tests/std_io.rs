@@ -0,0 +1,100 @@
+use pocket_knife_file_format::{Archivable, FileTable, StdArchive};
+
+use embedded_io::{Read, ReadExactError};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Cursor};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+type FileArchive = StdArchive<File>;
+
+// a directory of its own for each test, emptied again when it's dropped
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(name: &str) -> TempDir {
+        let path = std::env::temp_dir().join(format!("pocket-knife-{}-{}", std::process::id(), name));
+        fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+// fails every read with something other than running out
+struct Failing;
+
+impl io::Read for Failing {
+    fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+        Err(io::Error::new(io::ErrorKind::PermissionDenied, "no"))
+    }
+}
+
+#[test]
+fn running_out_is_told_apart_from_failing() {
+    let mut archive = StdArchive::new(Cursor::new(b"abc".to_vec()));
+    let mut buffer = [0u8; 2];
+    archive.read_exact(&mut buffer).unwrap();
+    assert_eq!(&buffer, b"ab");
+    assert!(matches!(archive.read_exact(&mut buffer), Err(ReadExactError::UnexpectedEof)));
+
+    match StdArchive::new(Failing).read_exact(&mut buffer) {
+        Err(ReadExactError::Other(err)) => assert_eq!(err.kind(), io::ErrorKind::PermissionDenied),
+        result => panic!("expected the read to fail, got {:?}", result),
+    }
+}
+
+#[test]
+fn paths_are_stored_under_their_file_name() {
+    let directory = TempDir::new("filenames");
+    let path = directory.0.join("a.txt");
+    assert_eq!(Archivable::<FileArchive>::filename(&path.as_path()).unwrap(), "a.txt");
+    assert_eq!(Archivable::<FileArchive>::filename(&path).unwrap(), "a.txt");
+    // there's no file name to use for the root
+    let error = Archivable::<FileArchive>::filename(&Path::new("/")).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+}
+
+#[cfg(unix)]
+#[test]
+fn file_names_have_to_be_utf_8() {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = PathBuf::from(OsStr::from_bytes(b"caf\xe9.txt"));
+    let error = Archivable::<FileArchive>::filename(&path).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn files_round_trip_through_an_archive_on_disk() {
+    let directory = TempDir::new("round-trip");
+    let before = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
+    let inputs: Vec<PathBuf> = [("a.txt", &b"hello, world"[..]), ("b.bin", &[7; 10_000])].iter()
+        .map(|(filename, contents)| {
+            let path = directory.0.join(filename);
+            fs::write(&path, contents).unwrap();
+            path
+        })
+        .collect();
+
+    let archive_path = directory.0.join("archive.pk");
+    let mut archive = StdArchive::new(File::create(&archive_path).unwrap());
+    let file_table = FileTable::create(&mut archive, &inputs).unwrap();
+
+    let mut archive = StdArchive::new(OpenOptions::new().read(true).open(&archive_path).unwrap());
+    let read = FileTable::read(&mut archive).unwrap();
+    assert_eq!(read, file_table);
+    for input in inputs.iter() {
+        let filename = input.file_name().unwrap().to_str().unwrap();
+        assert_eq!(read.open_file(&mut archive, filename.into()).unwrap(), fs::read(input).unwrap());
+        // creation times are in whole seconds, and fall back to the modification time where there aren't any
+        let created = read.0[filename].metadata.created.unwrap();
+        assert!((before - 1..=before + 1).contains(&created), "{} was created at {}, not around {}", filename, created, before);
+    }
+    read.verify(&mut archive).unwrap();
+}
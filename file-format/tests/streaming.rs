@@ -0,0 +1,52 @@
+mod common;
+use common::*;
+
+use pocket_knife_file_format::{CreateOptions, FileTable, Header, TRAILER_LENGTH};
+
+const FILES: &[(&str, &[u8])] = &[("a.txt", b"hello, world"), ("dir/b.txt", b"goodbye")];
+
+// a Vec can't seek, so anything written to one was written in a single pass
+fn stream(options: CreateOptions) -> (MemoryArchive, FileTable) {
+    let mut output = Vec::new();
+    let file_table = FileTable::create_streaming(&mut output, FILES, options).unwrap();
+    (MemoryArchive::new(&output), file_table)
+}
+
+#[test]
+fn streamed_archives_are_found_through_their_trailer() {
+    let (mut archive, file_table) = stream(CreateOptions::default());
+    let header = Header::read(&mut archive).unwrap();
+    assert!(header.has_trailer());
+    // the header was written before anything else was known
+    assert_eq!((header.table_address, header.table_length), (0, 0));
+
+    archive.position = 0;
+    assert_eq!(FileTable::read(&mut archive).unwrap(), file_table);
+    for (filename, contents) in FILES {
+        assert_eq!(file_table.open_file(&mut archive, filename.to_string()).unwrap(), *contents);
+    }
+    file_table.verify(&mut archive).unwrap();
+}
+
+#[test]
+fn streamed_archives_match_seeking_ones_apart_from_the_header_and_trailer() {
+    for options in [CreateOptions::default(), CreateOptions { alignment: 32, index: true, ..CreateOptions::default() }] {
+        let (streamed, streamed_table) = stream(options);
+        let (written, written_table) = pack(FILES, options);
+        assert_eq!(streamed_table, written_table);
+        let header_length = Header::new(Default::default(), 0).length() as usize;
+        let streamed_body = &streamed.bytes[header_length..streamed.bytes.len() - TRAILER_LENGTH as usize];
+        assert_eq!(streamed_body, &written.bytes[header_length..]);
+    }
+}
+
+#[test]
+fn streamed_archives_can_be_appended_to() {
+    let (mut archive, mut file_table) = stream(CreateOptions::default());
+    file_table.append(&mut archive, [("c.txt", b"again")]).unwrap();
+    archive.position = 0;
+    let read = FileTable::read(&mut archive).unwrap();
+    assert_eq!(read, file_table);
+    assert_eq!(read.open_file(&mut archive, "c.txt".into()).unwrap(), b"again");
+    assert_eq!(read.open_file(&mut archive, "a.txt".into()).unwrap(), b"hello, world");
+}
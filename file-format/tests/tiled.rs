@@ -0,0 +1,83 @@
+mod common;
+use common::*;
+
+use pocket_knife_file_format::{Rgb565Encoding, Rgb565Image, TiledImage, TiledImageError, TILED_HEADER_LENGTH};
+
+// 5x3 in 2x2 tiles, so the last column and row are cut short
+fn image() -> Rgb565Image {
+    Rgb565Image::new(5, 3, (0..15).collect())
+}
+
+fn encode(encoding: Rgb565Encoding) -> MemoryArchive {
+    MemoryArchive::new(&TiledImage::encode(&image(), 2, 2, encoding).unwrap())
+}
+
+#[test]
+fn tiles_are_the_image_cut_up() {
+    for encoding in [Rgb565Encoding::Raw, Rgb565Encoding::Rle] {
+        let mut reader = encode(encoding);
+        let tiled = TiledImage::read(&mut reader).unwrap();
+        assert_eq!((tiled.width, tiled.height, tiled.columns(), tiled.rows()), (5, 3, 3, 2));
+        let mut pixels = vec![0; 15];
+        for (column, row) in tiled.tiles_in(0, 0, 5, 3) {
+            let tile = tiled.read_tile(&mut reader, column, row).unwrap();
+            let (x, y) = tiled.tile_origin(column, row);
+            for (tile_y, tile_row) in tile.rows().enumerate() {
+                let start = (y as usize + tile_y) * 5 + x as usize;
+                pixels[start..start + tile_row.len()].copy_from_slice(tile_row);
+            }
+        }
+        assert_eq!(pixels, image().pixels);
+        assert!(matches!(tiled.read_tile(&mut reader, 3, 0), Err(TiledImageError::NoSuchTile { column: 3, row: 0 })));
+        assert!(matches!(tiled.read_tile(&mut reader, 0, 2), Err(TiledImageError::NoSuchTile { column: 0, row: 2 })));
+    }
+}
+
+#[test]
+fn regions_cover_the_tiles_they_touch() {
+    let tiled = TiledImage::read(&mut encode(Rgb565Encoding::Raw)).unwrap();
+    assert_eq!(tiled.tiles_in(1, 1, 2, 1).collect::<Vec<_>>(), [(0, 0), (1, 0)]);
+    assert_eq!(tiled.tiles_in(4, 2, u32::MAX, u32::MAX).collect::<Vec<_>>(), [(2, 1)]);
+    assert_eq!(tiled.tiles_in(5, 0, 1, 1).count(), 0);
+    assert_eq!(tiled.tiles_in(1, 1, 0, 1).count(), 0);
+}
+
+#[test]
+fn broken_directories_are_rejected() {
+    let bytes = encode(Rgb565Encoding::Raw).bytes;
+    let directory = TILED_HEADER_LENGTH as usize;
+
+    let mut not_tiled = bytes.clone();
+    not_tiled[0] = b'X';
+    assert!(matches!(TiledImage::read(&mut MemoryArchive::new(&not_tiled)), Err(TiledImageError::NotTiled)));
+
+    let mut zero_tiles = bytes.clone();
+    zero_tiles[12..16].copy_from_slice(&0u32.to_le_bytes());
+    assert!(matches!(TiledImage::read(&mut MemoryArchive::new(&zero_tiles)), Err(TiledImageError::InvalidDirectory)));
+
+    // far more tiles than there's room for a directory of
+    let mut too_many = bytes.clone();
+    too_many[4..12].copy_from_slice(&[0xff; 8]);
+    too_many[12..20].copy_from_slice(&[1, 0, 0, 0, 1, 0, 0, 0]);
+    assert!(matches!(TiledImage::read(&mut MemoryArchive::new(&too_many)), Err(TiledImageError::InvalidDirectory)));
+
+    let mut past_the_end = bytes.clone();
+    past_the_end[directory + 4..directory + 8].copy_from_slice(&u32::MAX.to_le_bytes());
+    assert!(matches!(TiledImage::read(&mut MemoryArchive::new(&past_the_end)), Err(TiledImageError::InvalidDirectory)));
+}
+
+#[test]
+fn tiles_have_to_fit_their_place() {
+    // the first tile's directory entry swapped with the one on the cut short right edge
+    let mut bytes = encode(Rgb565Encoding::Raw).bytes;
+    let directory = TILED_HEADER_LENGTH as usize;
+    let first = bytes[directory..directory + 8].to_vec();
+    bytes.copy_within(directory + 16..directory + 24, directory);
+    bytes[directory + 16..directory + 24].copy_from_slice(&first);
+
+    let mut reader = MemoryArchive::new(&bytes);
+    let tiled = TiledImage::read(&mut reader).unwrap();
+    assert!(matches!(tiled.read_tile(&mut reader, 0, 0), Err(TiledImageError::InvalidTile { column: 0, row: 0 })));
+    assert!(matches!(tiled.read_tile(&mut reader, 2, 0), Err(TiledImageError::InvalidTile { column: 2, row: 0 })));
+    tiled.read_tile(&mut reader, 1, 0).unwrap();
+}
@@ -0,0 +1,85 @@
+mod common;
+use common::*;
+
+use pocket_knife_file_format::{CreateError, CreateOptions, FileTable, Header, UpdateError, VERSION};
+
+const FILES: &[(&str, &[u8])] = &[("a.txt", b"hello, world"), ("b.txt", b"goodbye"), ("dir/c.txt", b"again")];
+
+#[test]
+fn renamed_entries_keep_their_contents() {
+    let (mut archive, mut file_table) = pack(FILES, CreateOptions::default());
+    file_table.rename(&mut archive, "a.txt".into(), "dir/a.txt".into()).unwrap();
+    // the last file in a directory can leave it for a file of the same name
+    file_table.rename(&mut archive, "dir/c.txt".into(), "c.txt".into()).unwrap();
+    file_table.rename(&mut archive, "b.txt".into(), "dir".into()).unwrap_err();
+
+    archive.position = 0;
+    let read = FileTable::read(&mut archive).unwrap();
+    assert_eq!(read, file_table);
+    assert_eq!(read.0.keys().collect::<Vec<_>>(), ["b.txt", "c.txt", "dir/a.txt"]);
+    assert_eq!(read.open_file(&mut archive, "dir/a.txt".into()).unwrap(), b"hello, world");
+    read.verify(&mut archive).unwrap();
+}
+
+#[test]
+fn renames_that_dont_fit_leave_the_archive_alone() {
+    let (mut archive, mut file_table) = pack(FILES, CreateOptions::default());
+    let before = archive.bytes.clone();
+
+    let mut rename = |from: &str, to: &str| file_table.rename(&mut archive, from.into(), to.into()).unwrap_err();
+    assert!(matches!(rename("a.txt", "b.txt"), UpdateError::Create(CreateError::DuplicateFilename(name)) if name == "b.txt"));
+    // onto a directory, and into a file as if it were one
+    assert!(matches!(rename("a.txt", "dir"), UpdateError::Create(CreateError::PathConflict(name)) if name == "dir"));
+    assert!(matches!(rename("a.txt", "b.txt/a.txt"), UpdateError::Create(CreateError::PathConflict(_))));
+    assert!(matches!(rename("a.txt", "dir/../a.txt"), UpdateError::Create(CreateError::InvalidPath(_))));
+    assert!(matches!(rename("missing", "a.txt"), UpdateError::NoSuchFile(name) if name == "missing"));
+
+    assert_eq!(archive.bytes, before);
+    archive.position = 0;
+    assert_eq!(FileTable::read(&mut archive).unwrap(), file_table);
+}
+
+#[test]
+fn versions_6_and_7_are_upgraded_as_theyre_edited() {
+    for fixture in [
+        &include_bytes!("fixtures/v6.pk")[..],
+        include_bytes!("fixtures/v6-index.pk"),
+        include_bytes!("fixtures/v7.pk"),
+        include_bytes!("fixtures/v7-index.pk"),
+    ] {
+        let mut archive = MemoryArchive::new(fixture);
+        let mut file_table = FileTable::read(&mut archive).unwrap();
+        let old_header = Header::read(&mut MemoryArchive::new(fixture)).unwrap();
+        file_table.rename(&mut archive, "dir/b.txt".into(), "renamed.txt".into()).unwrap();
+
+        archive.position = 0;
+        let header = Header::read(&mut archive).unwrap();
+        assert_eq!(header.version, VERSION);
+        assert_eq!((header.features, header.alignment), (old_header.features, old_header.alignment));
+        archive.position = 0;
+        let read = FileTable::read(&mut archive).unwrap();
+        assert_eq!(read, file_table);
+        assert_eq!(read.open_file(&mut archive, "renamed.txt".into()).unwrap(), b"b\n");
+        read.verify(&mut archive).unwrap();
+    }
+}
+
+#[test]
+fn versions_that_cant_be_upgraded_in_place_are_rejected() {
+    // version 5 has a shorter header
+    let fixture = include_bytes!("fixtures/v5.pk");
+    let mut archive = MemoryArchive::new(fixture);
+    let mut file_table = FileTable::read(&mut archive).unwrap();
+    let error = file_table.rename(&mut archive, "a.txt".into(), "renamed.txt".into()).unwrap_err();
+    assert!(matches!(error, UpdateError::OutdatedVersion(5)));
+    assert_eq!(archive.bytes, fixture);
+
+    // older versions ending in a trailer too, since only a header gets upgraded
+    let mut output = Vec::new();
+    let mut file_table = FileTable::create_streaming(&mut output, FILES, CreateOptions::default()).unwrap();
+    output[20..28].copy_from_slice(&7u64.to_le_bytes());
+    let mut archive = MemoryArchive::new(&output);
+    let error = file_table.rename(&mut archive, "a.txt".into(), "renamed.txt".into()).unwrap_err();
+    assert!(matches!(error, UpdateError::OutdatedVersion(7)));
+    assert_eq!(archive.bytes, output);
+}
@@ -0,0 +1,122 @@
+mod common;
+use common::*;
+
+use pocket_knife_file_format::{
+    CreateError, CreateOptions, FileTable, OpenError, ReadError, UpdateError, VolumeBuilder, VolumeSet,
+    VOLUME_HEADER_LENGTH,
+};
+
+use embedded_io::{ErrorKind, ErrorType, Seek, SeekFrom, Write};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+const MAX_VOLUME_SIZE: u64 = 100;
+
+const FILES: &[(&str, &[u8])] = &[
+    ("a", &[b'a'; 30]),
+    ("b", &[b'b'; 30]),
+    ("c", &[b'c'; 30]),
+    ("d", &[b'd'; 30]),
+    ("e", &[b'e'; 20]),
+];
+
+// a volume that's still around once the builder is done with it
+#[derive(Clone, Default)]
+struct SharedVolume(Rc<RefCell<MemoryArchive>>);
+
+impl ErrorType for SharedVolume {
+    type Error = ErrorKind;
+}
+
+impl Write for SharedVolume {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, ErrorKind> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> Result<(), ErrorKind> {
+        Ok(())
+    }
+}
+
+impl Seek for SharedVolume {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, ErrorKind> {
+        self.0.borrow_mut().seek(pos)
+    }
+}
+
+// writes FILES as volumes, returning them by number along with the table
+fn split(files: &[(&str, &[u8])]) -> (BTreeMap<u16, MemoryArchive>, Result<FileTable, CreateError<ErrorKind>>) {
+    let volumes = RefCell::new(BTreeMap::new());
+    let create_volume = |number| {
+        let volume = SharedVolume::default();
+        volumes.borrow_mut().insert(number, volume.clone());
+        Ok(volume)
+    };
+    let result = VolumeBuilder::new(MAX_VOLUME_SIZE, CreateOptions::default(), create_volume)
+        .and_then(|mut builder| {
+            for file in files {
+                builder.add_entry(file)?;
+            }
+            builder.finish()
+        });
+    let volumes = volumes.into_inner().into_iter()
+        .map(|(number, volume)| (number, MemoryArchive::new(&volume.0.borrow().bytes)))
+        .collect();
+    (volumes, result)
+}
+
+#[test]
+fn entries_are_split_between_volumes_and_read_back() {
+    let (volumes, file_table) = split(FILES);
+    let file_table = file_table.unwrap();
+    assert_eq!(volumes.keys().copied().collect::<Vec<_>>(), [0, 1, 2, 3]);
+    assert_eq!(file_table.volume_count(), 4);
+    for (number, volume) in volumes.iter().skip(1) {
+        assert!(volume.bytes.len() as u64 <= MAX_VOLUME_SIZE, "volume {}", number);
+        assert!(volume.bytes.len() as u64 > VOLUME_HEADER_LENGTH, "volume {}", number);
+    }
+    // entries go in the order they're added, each in the last volume if it fits there
+    let in_volumes = file_table.0.iter().map(|(filename, entry)| (filename.as_str(), entry.volume)).collect::<Vec<_>>();
+    assert_eq!(in_volumes, [("a", 1), ("b", 1), ("c", 2), ("d", 2), ("e", 3)]);
+
+    let (mut set, read) = VolumeSet::open(volumes[&0].clone(), |number| Ok(volumes[&number].clone())).unwrap();
+    assert_eq!(read, file_table);
+    assert_eq!(set.len(), 4);
+    for (filename, contents) in FILES {
+        assert_eq!(set.open_file(&read, filename.to_string()).unwrap(), *contents);
+    }
+    set.verify(&read).unwrap();
+}
+
+#[test]
+fn entries_need_the_volume_theyre_in() {
+    let (mut volumes, file_table) = split(FILES);
+    let file_table = file_table.unwrap();
+    let archive = volumes.get_mut(&0).unwrap();
+    assert!(matches!(
+        file_table.open_file(archive, "a".into()),
+        Err(OpenError::MissingVolume { volume: 1, .. }),
+    ));
+    let mut output = MemoryArchive::default();
+    assert!(matches!(file_table.compact(archive, &mut output), Err(UpdateError::HasVolumes)));
+}
+
+#[test]
+fn volumes_have_to_be_the_ones_asked_for() {
+    let (volumes, _) = split(FILES);
+    let swapped = VolumeSet::open(volumes[&0].clone(), |number| Ok(volumes[&(number % 3 + 1)].clone()));
+    assert!(matches!(swapped, Err(ReadError::InvalidVolume(1))));
+    let missing = VolumeSet::open(volumes[&0].clone(), |number| {
+        volumes.get(&number).filter(|_| number < 3).cloned().ok_or(ErrorKind::NotFound)
+    });
+    assert!(matches!(missing, Err(ReadError::OpenVolume(3, ErrorKind::NotFound))));
+    let truncated = VolumeSet::open(volumes[&0].clone(), |number| Ok(MemoryArchive::new(&volumes[&number].bytes[..10])));
+    assert!(matches!(truncated, Err(ReadError::ReadVolumeHeader(1, _))));
+}
+
+#[test]
+fn entries_too_large_for_any_volume_are_rejected() {
+    let (_, file_table) = split(&[("a", &[0; MAX_VOLUME_SIZE as usize])]);
+    assert!(matches!(file_table, Err(CreateError::EntryTooLarge(filename)) if filename == "a"));
+}
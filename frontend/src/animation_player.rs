@@ -0,0 +1,112 @@
+use crate::{rgb565_image, Backend, ImageLoadError, Volumes, UI};
+
+use pocket_knife_file_format::{Animation, Entry, EntryReader, Rgb565Image, ANIMATION_MEDIA_TYPE};
+
+use alloc::{format, rc::{Rc, Weak}, string::String};
+use core::{cell::RefCell, time::Duration};
+use slint::{ComponentHandle, Image, Timer, TimerMode};
+
+// plays animations (see animation.rs in the file format), reading each frame as it comes up. a slint timer waits
+// out every frame's duration, so playback runs on the backend's slint platform clock. the ui starts and stops
+// playback as menu items are shown and hidden, and gets every frame through its animation-frame property
+pub struct AnimationPlayer<B: Backend> {
+    volumes: Rc<Volumes<B>>,
+    ui: slint::Weak<UI>,
+    timer: Timer,
+    playing: RefCell<Option<Playing>>,
+}
+
+struct Playing {
+    filename: String,
+    entry: Entry,
+    animation: Animation,
+    frame: usize,
+    // times through the animation so far, counting this one
+    play: u32,
+}
+
+pub fn is_animation(entry: &Entry) -> bool {
+    entry.metadata.media_type.as_deref() == Some(ANIMATION_MEDIA_TYPE)
+}
+
+impl <B: Backend> AnimationPlayer<B> {
+    pub fn new(volumes: Rc<Volumes<B>>, ui: slint::Weak<UI>) -> Rc<AnimationPlayer<B>> {
+        Rc::new(AnimationPlayer { volumes, ui, timer: Timer::default(), playing: RefCell::default() })
+    }
+
+    // plays an animation from the beginning, instead of whatever was playing
+    pub fn start(self: &Rc<Self>, filename: String, entry: Entry) {
+        self.stop();
+        if let Err(err) = self.play(filename.clone(), entry) {
+            B::debug(format!("couldn't play {:?}: {}", filename, err));
+        }
+    }
+
+    pub fn stop(&self) {
+        self.timer.stop();
+        *self.playing.borrow_mut() = None;
+        if let Some(ui) = self.ui.upgrade() {
+            ui.set_animation_frame(Image::default());
+        }
+    }
+
+    fn play(self: &Rc<Self>, filename: String, entry: Entry) -> Result<(), ImageLoadError<B::Error>> {
+        let mut backend = self.volumes.backend(&entry)?;
+        let animation = Animation::read(&mut EntryReader::new(&mut backend, entry.clone()))?;
+        let image = rgb565_image(&animation.read_frame(&mut EntryReader::new(&mut backend, entry.clone()), 0)?);
+        self.wait(animation.duration(0).unwrap_or(0));
+        *self.playing.borrow_mut() = Some(Playing { filename, entry, animation, frame: 0, play: 1 });
+        self.show(image);
+        Ok(())
+    }
+
+    fn read_frame(&self, playing: &Playing, frame: usize) -> Result<Rgb565Image, ImageLoadError<B::Error>> {
+        let mut backend = self.volumes.backend(&playing.entry)?;
+        Ok(playing.animation.read_frame(&mut EntryReader::new(&mut backend, playing.entry.clone()), frame)?)
+    }
+
+    fn show(&self, image: Image) {
+        if let Some(ui) = self.ui.upgrade() {
+            ui.set_animation_frame(image);
+        }
+    }
+
+    fn wait(self: &Rc<Self>, duration: u32) {
+        // the timer belongs to the player, so it only holds on to it weakly
+        let player = Rc::downgrade(self);
+        self.timer.start(TimerMode::SingleShot, Duration::from_millis(duration as u64), move || {
+            if let Some(player) = Weak::upgrade(&player) {
+                player.next_frame();
+            }
+        });
+    }
+
+    fn next_frame(self: &Rc<Self>) {
+        let mut playing = self.playing.borrow_mut();
+        let Some(current) = playing.as_mut() else { return };
+
+        let mut frame = current.frame + 1;
+        if frame == current.animation.len() {
+            // the last frame stays up once the animation has played as many times as it should
+            if current.animation.plays != 0 && current.play >= current.animation.plays {
+                return;
+            }
+            frame = 0;
+            current.play += 1;
+        }
+        match self.read_frame(current, frame) {
+            Ok(image) => {
+                current.frame = frame;
+                let duration = current.animation.duration(frame).unwrap_or(0);
+                drop(playing);
+                self.wait(duration);
+                self.show(rgb565_image(&image));
+            },
+            Err(err) => {
+                B::debug(format!("couldn't play {:?}: {}", current.filename, err));
+                drop(playing);
+                self.stop();
+            },
+        }
+    }
+}
@@ -4,13 +4,16 @@ use alloc::{string::String, rc::Rc, format, boxed::Box};
 use chrono::{Datelike, NaiveDateTime, Timelike};
 use core::cell::RefCell;
 use embedded_io::{Read, Seek};
-use slint::platform::{software_renderer::{MinimalSoftwareWindow, Rgb565Pixel}, Platform};
+use slint::{platform::{software_renderer::{MinimalSoftwareWindow, Rgb565Pixel}, Platform}, PlatformError};
 
 pub trait Backend: 'static + Read + Seek + Clone {
     fn debug(message: String);
-    fn slint_platform(window: Rc<MinimalSoftwareWindow>) -> Box<dyn Platform + 'static>;
+    fn slint_platform(window: Rc<MinimalSoftwareWindow>) -> Result<Box<dyn Platform + 'static>, PlatformError>;
     fn blit(&self, buffer: [Rgb565Pixel; SCREEN_PIXELS]);
     fn interact_read(&self, interact_id: usize) -> u32;
     fn interact_changed(&self, interact_id: usize) -> bool;
     fn now(&self) -> NaiveDateTime;
+    // another backend reading from the start of one of the volumes the archive is split into (see volume.rs in the
+    // file format), numbered from 1
+    fn open_volume(&self, number: u16) -> Result<Self, Self::Error>;
 }
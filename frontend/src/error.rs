@@ -1,7 +1,14 @@
+use core::error::Error;
+use core::fmt::{self, Display, Formatter};
+
 #[derive(Debug)]
 pub enum ImageLoadError<E> {
     Filesystem(pocket_knife_file_format::OpenError<E>),
+    Volume(pocket_knife_file_format::ReadError<E>),
     Parse(tinybmp::ParseError),
+    Rgb565(pocket_knife_file_format::Rgb565DecodeError),
+    Tiled(pocket_knife_file_format::TiledImageError<pocket_knife_file_format::EntryReadError<E>>),
+    Animation(pocket_knife_file_format::AnimationError<pocket_knife_file_format::EntryReadError<E>>),
 }
 
 impl <E> From<pocket_knife_file_format::OpenError<E>> for ImageLoadError<E> {
@@ -10,8 +17,59 @@ impl <E> From<pocket_knife_file_format::OpenError<E>> for ImageLoadError<E> {
     }
 }
 
+impl <E> From<pocket_knife_file_format::ReadError<E>> for ImageLoadError<E> {
+    fn from(error: pocket_knife_file_format::ReadError<E>) -> Self {
+        ImageLoadError::Volume(error)
+    }
+}
+
 impl <E> From<tinybmp::ParseError> for ImageLoadError<E> {
     fn from(error: tinybmp::ParseError) -> Self {
         ImageLoadError::Parse(error)
     }
 }
+
+impl <E> From<pocket_knife_file_format::Rgb565DecodeError> for ImageLoadError<E> {
+    fn from(error: pocket_knife_file_format::Rgb565DecodeError) -> Self {
+        ImageLoadError::Rgb565(error)
+    }
+}
+
+impl <E> From<pocket_knife_file_format::TiledImageError<pocket_knife_file_format::EntryReadError<E>>> for ImageLoadError<E> {
+    fn from(error: pocket_knife_file_format::TiledImageError<pocket_knife_file_format::EntryReadError<E>>) -> Self {
+        ImageLoadError::Tiled(error)
+    }
+}
+
+impl <E> From<pocket_knife_file_format::AnimationError<pocket_knife_file_format::EntryReadError<E>>> for ImageLoadError<E> {
+    fn from(error: pocket_knife_file_format::AnimationError<pocket_knife_file_format::EntryReadError<E>>) -> Self {
+        ImageLoadError::Animation(error)
+    }
+}
+
+impl <E> Display for ImageLoadError<E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ImageLoadError::Filesystem(error) => Display::fmt(error, f),
+            ImageLoadError::Volume(error) => Display::fmt(error, f),
+            // tinybmp's errors don't implement Display
+            ImageLoadError::Parse(error) => write!(f, "couldn't decode the image: {:?}", error),
+            ImageLoadError::Rgb565(error) => Display::fmt(error, f),
+            ImageLoadError::Tiled(error) => Display::fmt(error, f),
+            ImageLoadError::Animation(error) => Display::fmt(error, f),
+        }
+    }
+}
+
+impl <E: Error + 'static> Error for ImageLoadError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ImageLoadError::Filesystem(error) => error.source(),
+            ImageLoadError::Volume(error) => error.source(),
+            ImageLoadError::Parse(_) => None,
+            ImageLoadError::Rgb565(error) => error.source(),
+            ImageLoadError::Tiled(error) => error.source(),
+            ImageLoadError::Animation(error) => error.source(),
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use crate::Backend;
+
+use pocket_knife_file_format::{is_level, is_playlist, Entry, FileTable, PagedIndex, ReadError, LEVELS_DIRECTORY, PATH_SEPARATOR, PLAYLISTS_DIRECTORY};
+
+use alloc::{collections::BTreeMap, rc::Rc, string::String, vec::Vec, format};
+use core::ops::Range;
+use core::cell::RefCell;
+use embedded_io::Seek;
+
+// how many entries get read from a paged index at once
+pub const PAGE_LENGTH: usize = 32;
+// enough pages for the menu on screen plus some scrolling either way
+pub const CACHED_PAGES: usize = 4;
+
+type Page = Rc<Vec<(String, Entry)>>;
+
+// the archive's files in filename order, for the menu (see menu.rs). archives with an index only get the pages the
+// menu actually shows read, anything else has its whole table read up front. image levels and playlists (see
+// levels.rs and playlist.rs in the file format) are left out, levels are only found by filename and playlists are
+// listed on their own
+pub enum FileList<B: Backend> {
+    Table {
+        entries: Vec<(String, Entry)>,
+        levels: BTreeMap<String, Entry>,
+        playlists: Vec<(String, Entry)>,
+    },
+    Paged {
+        backend: B,
+        index: PagedIndex,
+        // where the levels and playlists are in the index, each of them all sort together
+        levels: Range<usize>,
+        playlists: Range<usize>,
+        // most recently used first
+        pages: RefCell<Vec<(usize, Page)>>,
+    },
+}
+
+impl <B: Backend> FileList<B> {
+    pub fn read(backend: &B) -> Result<FileList<B>, ReadError<B::Error>> {
+        let mut archive = backend.clone();
+        match PagedIndex::open(&mut archive) {
+            Ok(index) => {
+                let levels = directory_range(&mut archive, &index, LEVELS_DIRECTORY)?;
+                let playlists = directory_range(&mut archive, &index, PLAYLISTS_DIRECTORY)?;
+                Ok(FileList::Paged { backend: archive, index, levels, playlists, pages: RefCell::default() })
+            },
+            Err(ReadError::NoIndex) => {
+                archive.rewind().map_err(ReadError::SeekToFileTable)?;
+                let file_table = FileTable::read(&mut archive)?;
+                let (levels, entries): (BTreeMap<_, _>, BTreeMap<_, _>) = file_table.0.into_iter()
+                    .partition(|(filename, _)| is_level(filename));
+                let (playlists, entries): (Vec<_>, Vec<_>) = entries.into_iter()
+                    .partition(|(filename, _)| is_playlist(filename));
+                Ok(FileList::Table { entries, levels, playlists })
+            },
+            Err(err) => Err(err),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            FileList::Table { entries, .. } => entries.len(),
+            FileList::Paged { index, levels, playlists, .. } => index.len() - levels.len() - playlists.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, position: usize) -> Option<(String, Entry)> {
+        match self {
+            FileList::Table { entries, .. } => entries.get(position).cloned(),
+            FileList::Paged { levels, playlists, .. } => {
+                // skipping over whichever of them sorts first, then the other
+                let (first, second) = if levels.start <= playlists.start { (levels, playlists) } else { (playlists, levels) };
+                let position = if position >= first.start { position + first.len() } else { position };
+                let position = if position >= second.start { position + second.len() } else { position };
+                let page = self.page(position / PAGE_LENGTH)?;
+                page.get(position % PAGE_LENGTH).cloned()
+            },
+        }
+    }
+
+    // any entry by filename, including the levels left out of the list
+    pub fn find(&self, filename: &str) -> Option<Entry> {
+        match self {
+            FileList::Table { entries, levels, .. } => levels.get(filename).cloned().or_else(|| {
+                let position = entries.binary_search_by(|(found, _)| found.as_str().cmp(filename)).ok()?;
+                Some(entries[position].1.clone())
+            }),
+            FileList::Paged { backend, index, .. } => match index.find(&mut backend.clone(), filename) {
+                Ok(entry) => entry,
+                Err(err) => {
+                    B::debug(format!("couldn't find {:?}: {:?}", filename, err));
+                    None
+                },
+            },
+        }
+    }
+
+    // the playlists' entries, by filename
+    pub fn playlists(&self) -> Vec<(String, Entry)> {
+        match self {
+            FileList::Table { playlists, .. } => playlists.clone(),
+            FileList::Paged { backend, index, playlists, .. } => {
+                match index.read_page(&mut backend.clone(), playlists.start, playlists.len()) {
+                    Ok(playlists) => playlists,
+                    Err(err) => {
+                        B::debug(format!("couldn't read the playlists: {:?}", err));
+                        Vec::new()
+                    },
+                }
+            },
+        }
+    }
+
+    fn page(&self, page_number: usize) -> Option<Page> {
+        let FileList::Paged { backend, index, pages, .. } = self else { return None };
+        let mut pages = pages.borrow_mut();
+
+        if let Some(cached) = pages.iter().position(|(number, _)| *number == page_number) {
+            let page = pages.remove(cached);
+            pages.insert(0, page);
+            return Some(pages[0].1.clone());
+        }
+
+        let page = match index.read_page(&mut backend.clone(), page_number * PAGE_LENGTH, PAGE_LENGTH) {
+            Ok(page) => Rc::new(page),
+            Err(err) => {
+                B::debug(format!("couldn't read page {}: {:?}", page_number, err));
+                return None;
+            },
+        };
+        pages.insert(0, (page_number, page.clone()));
+        pages.truncate(CACHED_PAGES);
+        Some(page)
+    }
+}
+
+// where the entries in a directory are in an index: everything from "<directory>/" up to the first name with the
+// character after the separator in its place
+fn directory_range<B: Backend>(archive: &mut B, index: &PagedIndex, directory: &str) -> Result<Range<usize>, ReadError<B::Error>> {
+    let after_separator = (PATH_SEPARATOR as u8 + 1) as char;
+    let start = index.position(archive, &format!("{}{}", directory, PATH_SEPARATOR))?;
+    let end = index.position(archive, &format!("{}{}", directory, after_separator))?;
+    Ok(start..end)
+}
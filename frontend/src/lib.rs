@@ -1,13 +1,23 @@
 #![no_std]
 #![allow(unused_imports)]
 
+mod animation_player;
 mod backend;
 mod error;
+mod file_list;
+mod menu;
+mod tiled_view;
+mod volumes;
 
+pub use animation_player::*;
 pub use backend::*;
 pub use error::*;
+pub use file_list::*;
+pub use menu::*;
+pub use tiled_view::*;
+pub use volumes::*;
 
-use pocket_knife_file_format::FileTable;
+use pocket_knife_file_format::{read_entry, Entry, Rgb565Image, RGB565_MEDIA_TYPE};
 
 extern crate alloc;
 
@@ -33,7 +43,11 @@ pub struct App<B: Backend> {
     pub backend: B,
     pub slint_window: Rc<MinimalSoftwareWindow>,
     pub ui: Rc<UI>,
-    pub file_table: Rc<FileTable>,
+    pub volumes: Rc<Volumes<B>>,
+    pub file_list: Rc<FileList<B>>,
+    pub menu: Rc<Menu<B>>,
+    pub tiled_view: Rc<TiledView<B>>,
+    pub animation_player: Rc<AnimationPlayer<B>>,
 }
 
 impl <B: Backend> App<B> {
@@ -41,41 +55,89 @@ impl <B: Backend> App<B> {
         let slint_window = MinimalSoftwareWindow::new(RepaintBufferType::NewBuffer);
         slint_window.set_size(PhysicalSize { width: SCREEN_WIDTH, height: SCREEN_HEIGHT });
 
-        slint::platform::set_platform(B::slint_platform(slint_window.clone())).unwrap();
+        slint::platform::set_platform(B::slint_platform(slint_window.clone()).unwrap()).unwrap();
 
         let ui = Rc::new(UI::new().unwrap());
 
-        let file_table = {
-            let mut backend = backend.clone();
-            let file_table = FileTable::read(&mut backend).unwrap();
-            Rc::from(file_table)
-        };
+        let volumes = Rc::new(Volumes::new(backend.clone()));
+        let file_list = Rc::new(FileList::read(&backend).unwrap());
+        let menu = Rc::new(Menu::new(&volumes, file_list.clone()));
+        let tiled_view = Rc::new(TiledView::new(volumes.clone()));
+        let animation_player = AnimationPlayer::new(volumes.clone(), ui.as_weak());
 
         {
             let slint_window = slint_window.clone();
             ui.on_request_redraw(move || slint_window.request_redraw());
         }
         {
-            let mut backend = backend.clone();
-            let file_table = file_table.clone();
-            ui.on_load_image(move |filename| load_image(&mut backend, &file_table, filename.into()))
+            let volumes = volumes.clone();
+            let menu = menu.clone();
+            ui.on_load_image(move |row, zoom| load_image(&volumes, &menu, row as usize, zoom as u32))
+        }
+        {
+            let menu = menu.clone();
+            ui.on_is_tiled(move |row, zoom| {
+                zoomed_entry(&menu, row as usize, zoom as u32).is_some_and(|(_, entry)| is_tiled(&entry))
+            })
+        }
+        {
+            let menu = menu.clone();
+            ui.on_is_animation(move |row| menu.entry(row as usize).is_some_and(|(_, entry)| is_animation(&entry)))
+        }
+        {
+            let menu = menu.clone();
+            let animation_player = animation_player.clone();
+            ui.on_start_animation(move |row| match menu.entry(row as usize).filter(|(_, entry)| is_animation(entry)) {
+                Some((filename, entry)) => animation_player.start(filename, entry),
+                None => animation_player.stop(),
+            })
+        }
+        {
+            let animation_player = animation_player.clone();
+            ui.on_stop_animation(move || animation_player.stop())
+        }
+        {
+            let menu = menu.clone();
+            ui.on_zoom_levels(move |row| menu.entry(row as usize).map_or(0, |(_, entry)| entry.metadata.levels.len() as i32))
+        }
+        {
+            let menu = menu.clone();
+            let tiled_view = tiled_view.clone();
+            ui.on_load_region(move |row, zoom, x, y| load_region(&tiled_view, &menu, row as usize, zoom as u32, x, y))
+        }
+        {
+            let menu = menu.clone();
+            let tiled_view = tiled_view.clone();
+            ui.on_max_pan_x(move |row, zoom| max_pan(&tiled_view, &menu, row as usize, zoom as u32).0)
+        }
+        {
+            let menu = menu.clone();
+            let tiled_view = tiled_view.clone();
+            ui.on_max_pan_y(move |row, zoom| max_pan(&tiled_view, &menu, row as usize, zoom as u32).1)
+        }
+
+        {
+            let menu = menu.clone();
+            ui.on_open_playlist(move |row| menu.open(row as usize))
+        }
+        {
+            let menu = menu.clone();
+            ui.on_close_playlist(move |row| menu.close().map_or(row, |playlist_row| playlist_row as i32))
         }
 
         ui.set_fallback_image(Image::from_rgb8(SharedPixelBuffer::new(0, 0)));
 
-        let filenames =
-            file_table.0.keys()
-                .map(SharedString::from)
-                .map(StandardListViewItem::from)
-                .collect::<Vec<_>>();
+        // rows are read as the menu needs them, so big archives don't have to fit in memory all at once
+        ui.set_menu_items(ModelRc::from(menu.clone()));
 
-        ui.set_filenames(ModelRc::from(filenames.as_slice()));
+        // the ui was set up before there was anything to play, so the first item has to be started here
+        ui.invoke_start_animation(0);
 
-        B::debug(format!("{:?}", filenames));
+        B::debug(format!("{} files", file_list.len()));
 
         ui.show().unwrap();
 
-        App { slint_window, ui, backend, file_table }
+        App { slint_window, ui, backend, volumes, file_list, menu, tiled_view, animation_player }
     }
 
     // todo: only update changed region from renderer
@@ -102,9 +164,63 @@ impl <B: Backend> App<B> {
     }
 }
 
-fn load_image(backend: &mut impl Backend, file_table: &FileTable, filename: String) -> Image {
-    let bytes = file_table.open_file(backend, filename).unwrap();
-    let bmp: Bmp<Rgb888> = Bmp::from_slice(&bytes).unwrap();
+fn load_image<B: Backend>(volumes: &Volumes<B>, menu: &Menu<B>, row: usize, zoom: u32) -> Image {
+    let Some((filename, entry)) = zoomed_entry(menu, row, zoom) else { return Image::default() };
+    match decode_image(volumes, filename.clone(), &entry) {
+        Ok(image) => image,
+        Err(err) => {
+            B::debug(format!("couldn't load {:?}: {}", filename, err));
+            Image::default()
+        },
+    }
+}
+
+// the viewport's offsets into the image, which are negative as it's panned right and down
+fn load_region<B: Backend>(tiled_view: &TiledView<B>, menu: &Menu<B>, row: usize, zoom: u32, x: f32, y: f32) -> Image {
+    let Some((filename, entry)) = zoomed_entry(menu, row, zoom) else { return Image::default() };
+    match tiled_view.region(filename.clone(), &entry, (-x).max(0.0) as u32, (-y).max(0.0) as u32) {
+        Ok(image) => image,
+        Err(err) => {
+            B::debug(format!("couldn't load {:?}: {}", filename, err));
+            Image::default()
+        },
+    }
+}
+
+// in the viewport's units, nothing if the image can't be read
+fn max_pan<B: Backend>(tiled_view: &TiledView<B>, menu: &Menu<B>, row: usize, zoom: u32) -> (f32, f32) {
+    let Some((filename, entry)) = zoomed_entry(menu, row, zoom) else { return (0.0, 0.0) };
+    match tiled_view.max_offset(filename.clone(), &entry) {
+        Ok((x, y)) => (x as f32, y as f32),
+        Err(err) => {
+            B::debug(format!("couldn't load {:?}: {}", filename, err));
+            (0.0, 0.0)
+        },
+    }
+}
+
+// what to show for a menu item zoomed out this many times, each one halving the size: the smallest of the image's
+// levels that's still big enough, or the image itself
+fn zoomed_entry<B: Backend>(menu: &Menu<B>, row: usize, zoom: u32) -> Option<(String, Entry)> {
+    let (filename, entry) = menu.entry(row)?;
+    let Some(image) = entry.metadata.image.filter(|_| zoom > 0) else { return Some((filename, entry)) };
+    let width = image.width.div_ceil(1 << zoom.min(31));
+    let Some(level) = entry.metadata.level_for_width(width) else { return Some((filename, entry)) };
+    match menu.find(&level.filename) {
+        Some(level_entry) => Some((level.filename.clone(), level_entry)),
+        None => {
+            B::debug(format!("{:?} is missing its level {:?}", filename, level.filename));
+            Some((filename, entry))
+        },
+    }
+}
+
+fn decode_image<B: Backend>(volumes: &Volumes<B>, filename: String, entry: &Entry) -> Result<Image, ImageLoadError<B::Error>> {
+    let bytes = read_entry(&mut volumes.backend(entry)?, filename, entry)?;
+    if entry.metadata.media_type.as_deref() == Some(RGB565_MEDIA_TYPE) {
+        return Ok(rgb565_image(&Rgb565Image::decode(&bytes)?));
+    }
+    let bmp: Bmp<Rgb888> = Bmp::from_slice(&bytes)?;
     let mut buffer: SharedPixelBuffer<Rgb8Pixel> = SharedPixelBuffer::new(bmp.size().width, bmp.size().height);
     {
         let mut buffer_mut = buffer.make_mut_slice().chunks_exact_mut(bmp.size().width as usize).collect::<Vec<_>>();
@@ -112,5 +228,24 @@ fn load_image(backend: &mut impl Backend, file_table: &FileTable, filename: Stri
             buffer_mut[pixel.0.y as usize][pixel.0.x as usize] = Rgb8Pixel::from(pixel.1.to_ne_bytes());
         }
     }
+    Ok(Image::from_rgb8(buffer))
+}
+
+// native images are already in the framebuffer's format, but slint only takes images as 8 bits per channel
+// (SharedPixelBuffer has no rgb565 pixel, and neither do the software renderer's textures), so they get widened
+// to rgb8 here and narrowed back to rgb565 as they're drawn. that's half again as much memory per decoded image
+fn rgb565_image(image: &Rgb565Image) -> Image {
+    let mut buffer: SharedPixelBuffer<Rgb8Pixel> = SharedPixelBuffer::new(image.width, image.height);
+    let rows = buffer.make_mut_slice().chunks_exact_mut(image.width.max(1) as usize);
+    for (buffer_row, row) in rows.zip(image.rows()) {
+        for (buffer_pixel, &pixel) in buffer_row.iter_mut().zip(row) {
+            *buffer_pixel = rgb8_pixel(pixel);
+        }
+    }
     Image::from_rgb8(buffer)
 }
+
+fn rgb8_pixel(pixel: u16) -> Rgb8Pixel {
+    let [r, g, b] = [pixel >> 11, (pixel >> 5) & 0x3f, pixel & 0x1f].map(|channel| channel as u8);
+    Rgb8Pixel::new((r << 3) | (r >> 2), (g << 2) | (g >> 4), (b << 3) | (b >> 2))
+}
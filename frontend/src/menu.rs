@@ -0,0 +1,118 @@
+use crate::{Backend, FileList, Volumes};
+
+use pocket_knife_file_format::{read_entry, Entry, Playlist};
+
+use alloc::{format, rc::Rc, string::String, vec::Vec};
+use core::cell::RefCell;
+use slint::{Model, ModelNotify, ModelTracker, SharedString, StandardListViewItem};
+
+// the menu's model: the archive's playlists (see playlist.rs in the file format) ahead of all of its files, or the
+// items of whichever playlist is open. rows are what the ui passes back to load images, so they always mean
+// whatever is listed right now
+pub struct Menu<B: Backend> {
+    file_list: Rc<FileList<B>>,
+    playlists: Vec<Playlist>,
+    open: RefCell<Option<OpenPlaylist>>,
+    notify: ModelNotify,
+}
+
+struct OpenPlaylist {
+    // the playlist's row in the menu, to go back to
+    row: usize,
+    // the items whose entries are still in the archive, with what to show for them
+    items: Vec<(SharedString, String, Entry)>,
+}
+
+impl <B: Backend> Menu<B> {
+    pub fn new(volumes: &Volumes<B>, file_list: Rc<FileList<B>>) -> Menu<B> {
+        let playlists = file_list.playlists().into_iter()
+            .filter_map(|(filename, entry)| {
+                let playlist = volumes.backend(&entry).map_err(|err| format!("{}", err))
+                    .and_then(|mut backend| read_entry(&mut backend, filename.clone(), &entry).map_err(|err| format!("{}", err)))
+                    .and_then(|bytes| Playlist::decode(&bytes).map_err(|err| format!("{}", err)));
+                match playlist {
+                    Ok(playlist) => Some(playlist),
+                    Err(err) => {
+                        B::debug(format!("couldn't read playlist {:?}: {}", filename, err));
+                        None
+                    },
+                }
+            })
+            .collect();
+        Menu { file_list, playlists, open: RefCell::default(), notify: ModelNotify::default() }
+    }
+
+    pub fn len(&self) -> usize {
+        match self.open.borrow().as_ref() {
+            Some(open) => open.items.len(),
+            None => self.playlists.len() + self.file_list.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // the file at a row, none for playlists
+    pub fn entry(&self, row: usize) -> Option<(String, Entry)> {
+        match self.open.borrow().as_ref() {
+            Some(open) => open.items.get(row).map(|(_, filename, entry)| (filename.clone(), entry.clone())),
+            None => self.file_list.get(row.checked_sub(self.playlists.len())?),
+        }
+    }
+
+    // any entry by filename, see FileList::find
+    pub fn find(&self, filename: &str) -> Option<Entry> {
+        self.file_list.find(filename)
+    }
+
+    // lists a playlist's items instead, if the row is one. false for anything else
+    pub fn open(&self, row: usize) -> bool {
+        if self.open.borrow().is_some() {
+            return false;
+        }
+        let Some(playlist) = self.playlists.get(row) else { return false };
+        let items = playlist.items.iter()
+            .filter_map(|item| {
+                let Some(entry) = self.file_list.find(&item.filename) else {
+                    B::debug(format!("{:?} is missing {:?}", playlist.title, item.filename));
+                    return None;
+                };
+                let title = item.title.as_deref().unwrap_or(&item.filename);
+                Some((SharedString::from(title), item.filename.clone(), entry))
+            })
+            .collect();
+        *self.open.borrow_mut() = Some(OpenPlaylist { row, items });
+        self.notify.reset();
+        true
+    }
+
+    // goes back to listing everything, giving the row of the playlist that was open
+    pub fn close(&self) -> Option<usize> {
+        let open = self.open.borrow_mut().take()?;
+        self.notify.reset();
+        Some(open.row)
+    }
+}
+
+impl <B: Backend> Model for Menu<B> {
+    type Data = StandardListViewItem;
+
+    fn row_count(&self) -> usize {
+        self.len()
+    }
+
+    fn row_data(&self, row: usize) -> Option<StandardListViewItem> {
+        if let Some(open) = self.open.borrow().as_ref() {
+            return open.items.get(row).map(|(title, _, _)| StandardListViewItem::from(title.clone()));
+        }
+        match self.playlists.get(row) {
+            Some(playlist) => Some(StandardListViewItem::from(SharedString::from(format!("[{}]", playlist.title)))),
+            None => self.entry(row).map(|(filename, _)| StandardListViewItem::from(SharedString::from(filename))),
+        }
+    }
+
+    fn model_tracker(&self) -> &dyn ModelTracker {
+        &self.notify
+    }
+}
@@ -0,0 +1,97 @@
+use crate::{rgb8_pixel, Backend, ImageLoadError, Volumes, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+use pocket_knife_file_format::{Entry, EntryReader, Rgb565Image, TiledImage, TILED_MEDIA_TYPE};
+
+use alloc::{rc::Rc, string::String, vec::Vec};
+use core::cell::RefCell;
+use slint::{Image, Rgb8Pixel, SharedPixelBuffer};
+
+// with 64 pixel tiles the screen overlaps at most 6 columns and 5 rows of them, this keeps one more of each
+// around so panning back doesn't read them again
+pub const CACHED_TILES: usize = 7 * 6;
+
+type Tile = Rc<Rgb565Image>;
+
+// shows tiled images (see tiled.rs in the file format) a screen at a time, only reading the tiles that are
+// actually on screen. tiles are read straight from the entry, so their checksum isn't checked
+pub struct TiledView<B: Backend> {
+    volumes: Rc<Volumes<B>>,
+    // the image being panned around, by filename
+    image: RefCell<Option<(String, Rc<TiledImage>)>>,
+    // tiles of that image by (column, row), most recently used first
+    tiles: RefCell<Vec<((u32, u32), Tile)>>,
+}
+
+pub fn is_tiled(entry: &Entry) -> bool {
+    entry.metadata.media_type.as_deref() == Some(TILED_MEDIA_TYPE)
+}
+
+impl <B: Backend> TiledView<B> {
+    pub fn new(volumes: Rc<Volumes<B>>) -> TiledView<B> {
+        TiledView { volumes, image: RefCell::default(), tiles: RefCell::default() }
+    }
+
+    // how far right and down the screen can go before it would be past the image's edges
+    pub fn max_offset(&self, filename: String, entry: &Entry) -> Result<(u32, u32), ImageLoadError<B::Error>> {
+        let image = self.image(filename, entry)?;
+        Ok((image.width.saturating_sub(SCREEN_WIDTH), image.height.saturating_sub(SCREEN_HEIGHT)))
+    }
+
+    // the part of the image at x, y that fits on the screen. x and y are moved back inside the image if the
+    // screen would go past its edges
+    pub fn region(&self, filename: String, entry: &Entry, x: u32, y: u32) -> Result<Image, ImageLoadError<B::Error>> {
+        let image = self.image(filename, entry)?;
+        let width = image.width.min(SCREEN_WIDTH);
+        let height = image.height.min(SCREEN_HEIGHT);
+        let x = x.min(image.width - width);
+        let y = y.min(image.height - height);
+
+        let mut buffer: SharedPixelBuffer<Rgb8Pixel> = SharedPixelBuffer::new(width, height);
+        let mut rows = buffer.make_mut_slice().chunks_exact_mut(width.max(1) as usize).collect::<Vec<_>>();
+        for (column, row) in image.tiles_in(x, y, width, height) {
+            let tile = self.tile(&image, entry, column, row)?;
+            let (tile_x, tile_y) = image.tile_origin(column, row);
+            // the part of the tile that's on screen, in image coordinates
+            let (left, right) = (tile_x.max(x), (tile_x + tile.width).min(x + width));
+            let (top, bottom) = (tile_y.max(y), (tile_y + tile.height).min(y + height));
+            for image_y in top..bottom {
+                let source = &tile.row(image_y - tile_y)[(left - tile_x) as usize..(right - tile_x) as usize];
+                let destination = &mut rows[(image_y - y) as usize][(left - x) as usize..(right - x) as usize];
+                for (pixel, &source) in destination.iter_mut().zip(source) {
+                    *pixel = rgb8_pixel(source);
+                }
+            }
+        }
+        Ok(Image::from_rgb8(buffer))
+    }
+
+    // the tile directory of the image, read again only when it's a different image than last time
+    fn image(&self, filename: String, entry: &Entry) -> Result<Rc<TiledImage>, ImageLoadError<B::Error>> {
+        let mut image = self.image.borrow_mut();
+        if let Some((current, tiled_image)) = image.as_ref() {
+            if *current == filename {
+                return Ok(tiled_image.clone());
+            }
+        }
+        let mut backend = self.volumes.backend(entry)?;
+        let tiled_image = Rc::new(TiledImage::read(&mut EntryReader::new(&mut backend, entry.clone()))?);
+        *image = Some((filename, tiled_image.clone()));
+        self.tiles.borrow_mut().clear();
+        Ok(tiled_image)
+    }
+
+    fn tile(&self, image: &TiledImage, entry: &Entry, column: u32, row: u32) -> Result<Tile, ImageLoadError<B::Error>> {
+        let mut tiles = self.tiles.borrow_mut();
+        if let Some(cached) = tiles.iter().position(|(position, _)| *position == (column, row)) {
+            let tile = tiles.remove(cached);
+            tiles.insert(0, tile);
+            return Ok(tiles[0].1.clone());
+        }
+
+        let mut backend = self.volumes.backend(entry)?;
+        let tile = Rc::new(image.read_tile(&mut EntryReader::new(&mut backend, entry.clone()), column, row)?);
+        tiles.insert(0, ((column, row), tile.clone()));
+        tiles.truncate(CACHED_TILES);
+        Ok(tile)
+    }
+}
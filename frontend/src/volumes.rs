@@ -0,0 +1,33 @@
+use crate::Backend;
+
+use pocket_knife_file_format::{read_volume_header, Entry, ReadError};
+
+use alloc::collections::BTreeMap;
+use core::cell::RefCell;
+
+// the volumes an archive is split into (see volume.rs in the file format), each opened through the backend and
+// checked the first time an entry in it is read. archives that aren't split into volumes only ever use the
+// backend itself, which is volume 0
+pub struct Volumes<B: Backend> {
+    // by volume number, only the ones opened so far
+    volumes: RefCell<BTreeMap<u16, B>>,
+}
+
+impl <B: Backend> Volumes<B> {
+    pub fn new(backend: B) -> Volumes<B> {
+        Volumes { volumes: RefCell::new(BTreeMap::from([(0, backend)])) }
+    }
+
+    // what to read an entry from, with its offsets into whichever volume it's in
+    pub fn backend(&self, entry: &Entry) -> Result<B, ReadError<B::Error>> {
+        let number = entry.volume;
+        if let Some(volume) = self.volumes.borrow().get(&number) {
+            return Ok(volume.clone());
+        }
+        let mut volume = self.volumes.borrow()[&0].open_volume(number)
+            .map_err(|err| ReadError::OpenVolume(number, err))?;
+        read_volume_header(&mut volume, number)?;
+        self.volumes.borrow_mut().insert(number, volume.clone());
+        Ok(volume)
+    }
+}
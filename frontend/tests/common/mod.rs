@@ -0,0 +1,53 @@
+// every test binary compiles this, and none of them use all of it
+#![allow(dead_code)]
+
+use pocket_knife_frontend::{Backend, SCREEN_PIXELS};
+
+use chrono::NaiveDateTime;
+use embedded_io::ErrorKind;
+use slint::platform::{software_renderer::{MinimalSoftwareWindow, Rgb565Pixel}, Platform};
+use slint::PlatformError;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+// the file format's in-memory archive, which is all file lists and menus need of a backend
+#[path = "../../../file-format/tests/common/mod.rs"]
+mod archive;
+pub use archive::*;
+
+thread_local! {
+    static MESSAGES: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+// what's been passed to debug on this thread since the last call
+pub fn take_messages() -> Vec<String> {
+    MESSAGES.with(|messages| messages.take())
+}
+
+impl Backend for MemoryArchive {
+    fn debug(message: String) {
+        MESSAGES.with(|messages| messages.borrow_mut().push(message));
+    }
+
+    fn slint_platform(_: Rc<MinimalSoftwareWindow>) -> Result<Box<dyn Platform + 'static>, PlatformError> {
+        Err(PlatformError::NoPlatform)
+    }
+
+    fn blit(&self, _: [Rgb565Pixel; SCREEN_PIXELS]) {}
+
+    fn interact_read(&self, _: usize) -> u32 {
+        0
+    }
+
+    fn interact_changed(&self, _: usize) -> bool {
+        false
+    }
+
+    fn now(&self) -> NaiveDateTime {
+        NaiveDateTime::default()
+    }
+
+    fn open_volume(&self, _: u16) -> Result<Self, ErrorKind> {
+        Err(ErrorKind::NotFound)
+    }
+}
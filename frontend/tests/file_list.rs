@@ -0,0 +1,78 @@
+mod common;
+use common::*;
+
+use pocket_knife_frontend::*;
+use pocket_knife_file_format::{level_filename, playlist_filename, CreateOptions, FileTable};
+
+// the filenames a file list should have, in order
+fn listed() -> Vec<String> {
+    let mut listed: Vec<String> = filenames().into_iter()
+        .filter(|filename| !filename.starts_with(".levels/") && !filename.starts_with(".playlists/"))
+        .collect();
+    listed.sort();
+    listed
+}
+
+// files sorting before, between and after the levels and playlists, with enough of everything to take several pages
+fn filenames() -> Vec<String> {
+    let mut filenames = Vec::new();
+    for n in 0..40 {
+        filenames.push(format!("-{:02}", n));
+        filenames.push(format!(".m{:02}", n));
+        filenames.push(format!("file{:02}", n));
+        filenames.push(level_filename(&format!("file{:02}", n), 1));
+        filenames.push(playlist_filename(&format!("{:02}", n)));
+    }
+    filenames
+}
+
+fn file_list(index: bool) -> FileList<MemoryArchive> {
+    let files: Vec<(String, Vec<u8>)> = filenames().into_iter().map(|filename| (filename.clone(), filename.into_bytes())).collect();
+    let mut archive = MemoryArchive::default();
+    let options = CreateOptions { index, ..CreateOptions::default() };
+    FileTable::create_with_options(&mut archive, &files, options).unwrap();
+    archive.position = 0;
+    FileList::read(&archive).unwrap()
+}
+
+#[test]
+fn levels_and_playlists_are_left_out() {
+    let expected = listed();
+
+    for index in [false, true] {
+        let list = file_list(index);
+        assert_eq!(list.len(), expected.len());
+        let listed: Vec<String> = (0..list.len()).map(|position| list.get(position).unwrap().0).collect();
+        assert_eq!(listed, expected, "index: {}", index);
+        assert!(list.get(list.len()).is_none());
+
+        let playlists: Vec<String> = list.playlists().into_iter().map(|(filename, _)| filename).collect();
+        assert_eq!(playlists, (0..40).map(|n| playlist_filename(&format!("{:02}", n))).collect::<Vec<_>>());
+        let level = level_filename("file07", 1);
+        assert_eq!(list.find(&level).unwrap().length, level.len() as u64);
+        assert!(list.find("file40").is_none());
+    }
+}
+
+#[test]
+fn only_archives_with_an_index_are_paged() {
+    assert!(matches!(file_list(false), FileList::Table { .. }));
+    assert!(matches!(file_list(true), FileList::Paged { .. }));
+}
+
+#[test]
+fn pages_are_read_as_theyre_needed() {
+    let list = file_list(true);
+    let FileList::Paged { pages, .. } = &list else { panic!("expected a paged list") };
+    assert!(pages.borrow().is_empty());
+
+    // backwards and then forwards, so pages get dropped from the cache and read again
+    let expected = listed();
+    let positions: Vec<usize> = (0..list.len()).rev().chain(0..list.len()).collect();
+    for position in positions {
+        assert_eq!(list.get(position).unwrap().0, expected[position], "{}", position);
+        assert!(pages.borrow().len() <= CACHED_PAGES);
+    }
+    assert_eq!(pages.borrow().len(), CACHED_PAGES);
+    assert!(take_messages().is_empty());
+}
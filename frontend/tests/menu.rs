@@ -0,0 +1,75 @@
+mod common;
+use common::*;
+
+use pocket_knife_frontend::*;
+use pocket_knife_file_format::{playlist_filename, ArchiveBuilder, CreateOptions, EntryOptions, Playlist, PlaylistItem};
+
+use slint::Model;
+use std::rc::Rc;
+
+// three pages, a playlist of them (and of one that's gone) and a playlist that isn't one
+fn menu(index: bool) -> Menu<MemoryArchive> {
+    let playlist = Playlist {
+        title: "In order".into(),
+        items: vec![
+            PlaylistItem { filename: "page2".into(), title: None },
+            PlaylistItem { filename: "gone".into(), title: None },
+            PlaylistItem { filename: "page10".into(), title: Some("The last page".into()) },
+        ],
+    };
+    let mut archive = MemoryArchive::default();
+    let mut builder = ArchiveBuilder::new(&mut archive, CreateOptions { index, ..CreateOptions::default() }).unwrap();
+    for filename in ["page10", "page2", "notes.txt"] {
+        builder.add_bytes(filename.into(), filename.as_bytes(), EntryOptions::default()).unwrap();
+    }
+    builder.add_bytes(playlist_filename("in order"), &playlist.encode().unwrap(), EntryOptions::default()).unwrap();
+    builder.add_bytes(playlist_filename("broken"), b"not a playlist", EntryOptions::default()).unwrap();
+    builder.finish().unwrap();
+
+    archive.position = 0;
+    let file_list = Rc::new(FileList::read(&archive).unwrap());
+    Menu::new(&Volumes::new(archive), file_list)
+}
+
+fn rows(menu: &Menu<MemoryArchive>) -> Vec<String> {
+    (0..menu.row_count()).map(|row| menu.row_data(row).unwrap().text.into()).collect()
+}
+
+#[test]
+fn playlists_are_listed_ahead_of_the_files() {
+    for index in [false, true] {
+        let menu = menu(index);
+        let messages = take_messages();
+        assert_eq!(messages.len(), 1, "{:?}", messages);
+        assert!(messages[0].starts_with("couldn't read playlist \".playlists/broken\""), "{:?}", messages);
+
+        assert_eq!(rows(&menu), ["[In order]", "notes.txt", "page10", "page2"], "index: {}", index);
+        assert!(menu.row_data(4).is_none());
+        assert!(menu.entry(0).is_none());
+        assert_eq!(menu.entry(3).unwrap().0, "page2");
+        assert_eq!(menu.find("page10").unwrap().length, 6);
+    }
+}
+
+#[test]
+fn playlists_open_in_place_of_the_files() {
+    for index in [false, true] {
+        let menu = menu(index);
+        take_messages();
+        // files aren't playlists, and neither are rows past the end
+        assert!(!menu.open(1));
+        assert!(!menu.open(4));
+        assert_eq!(menu.close(), None);
+
+        assert!(menu.open(0));
+        assert_eq!(take_messages(), ["\"In order\" is missing \"gone\""]);
+        assert_eq!(rows(&menu), ["page2", "The last page"], "index: {}", index);
+        assert_eq!(menu.entry(1).unwrap().0, "page10");
+        assert!(menu.entry(2).is_none());
+        // only one playlist is open at a time
+        assert!(!menu.open(0));
+
+        assert_eq!(menu.close(), Some(0));
+        assert_eq!(menu.len(), 4);
+    }
+}
@@ -0,0 +1,170 @@
+use pocket_knife_file_format::{AnimationFrame, ImageInfo, PixelFormat, Rgb565Image, ThumbnailImage};
+
+use embedded_graphics::{geometry::{OriginDimensions, Size}, pixelcolor::{IntoStorage, Rgb565, Rgb888, RgbColor}, Pixel};
+use image::{codecs::{gif::GifDecoder, png::PngDecoder}, metadata::LoopCount, AnimationDecoder, Frames};
+use std::io::Cursor;
+use tinybmp::{Bmp, Bpp, ChannelMasks, RawBmp};
+
+pub const BMP_MEDIA_TYPE: &str = "image/bmp";
+
+// the Pocket's screen, in pixels
+pub const SCREEN_WIDTH: u32 = 266;
+pub const SCREEN_HEIGHT: u32 = 240;
+
+pub fn bmp_image_info(bytes: &[u8]) -> Option<ImageInfo> {
+    let header = *RawBmp::from_slice(bytes).ok()?.header();
+    let pixel_format = match header.bpp {
+        Bpp::Bits1 => PixelFormat::Indexed1,
+        Bpp::Bits4 => PixelFormat::Indexed4,
+        Bpp::Bits8 => PixelFormat::Indexed8,
+        Bpp::Bits16 if header.channel_masks == Some(ChannelMasks::RGB565) => PixelFormat::Rgb565,
+        Bpp::Bits16 => PixelFormat::Rgb555,
+        Bpp::Bits24 => PixelFormat::Rgb888,
+        Bpp::Bits32 => PixelFormat::Xrgb8888,
+        _ => return None,
+    };
+    Some(ImageInfo {
+        width: header.image_size.width,
+        height: header.image_size.height,
+        pixel_format,
+    })
+}
+
+// as far as bmp_size needs to read
+pub const BMP_HEADER_LENGTH: usize = 30;
+
+// the size a BMP image says it is, from just the start of the file. the same headers as bmp_image_info are
+// accepted, but nothing past them is checked
+pub fn bmp_size(header: &[u8]) -> Option<(u32, u32)> {
+    let header = header.get(..BMP_HEADER_LENGTH)?;
+    let u32_at = |offset: usize| u32::from_le_bytes(header[offset..offset + 4].try_into().unwrap());
+    let bpp = u16::from_le_bytes([header[28], header[29]]);
+    let known_header = matches!(u32_at(14), 40 | 56 | 108 | 124);
+    if header[..2] != *b"BM" || !known_header || !matches!(bpp, 1 | 4 | 8 | 16 | 24 | 32) {
+        return None;
+    }
+    // negative heights are top-down images
+    let (width, height) = (u32_at(18) as i32, u32_at(22) as i32);
+    if width <= 0 || height == 0 {
+        return None;
+    }
+    Some((width as u32, height.unsigned_abs()))
+}
+
+// box filter down to fit within max_size square, keeping the aspect ratio
+pub fn bmp_thumbnail(bytes: &[u8], max_size: u32) -> Option<ThumbnailImage> {
+    let bmp: Bmp<Rgb888> = Bmp::from_slice(bytes).ok()?;
+    let Size { width, height } = bmp.size();
+    if width == 0 || height == 0 {
+        return None;
+    }
+
+    let scale = (max_size as f64 / width.max(height) as f64).min(1.0);
+    let thumbnail_width = ((width as f64 * scale).round() as u32).max(1);
+    let thumbnail_height = ((height as f64 * scale).round() as u32).max(1);
+    let pixels = box_filter(&bmp, thumbnail_width, thumbnail_height);
+
+    Some(ThumbnailImage { width: thumbnail_width, height: thumbnail_height, pixels })
+}
+
+// sizes of an image's levels, each half the size of the one before, until one fits on the screen
+pub fn level_sizes(width: u32, height: u32) -> Vec<(u32, u32)> {
+    let mut sizes = Vec::new();
+    let (mut level_width, mut level_height) = (width, height);
+    while level_width > SCREEN_WIDTH || level_height > SCREEN_HEIGHT {
+        (level_width, level_height) = (level_width.div_ceil(2), level_height.div_ceil(2));
+        sizes.push((level_width, level_height));
+    }
+    sizes
+}
+
+// level 1 is the first downscaled one, see level_sizes
+pub fn bmp_level(bytes: &[u8], level: u32) -> Option<Rgb565Image> {
+    let bmp: Bmp<Rgb888> = Bmp::from_slice(bytes).ok()?;
+    let Size { width, height } = bmp.size();
+    let &(level_width, level_height) = level_sizes(width, height).get(level.checked_sub(1)? as usize)?;
+    Some(Rgb565Image::new(level_width, level_height, box_filter(&bmp, level_width, level_height)))
+}
+
+// averages every source pixel into the scaled down pixel it lands in
+fn box_filter(bmp: &Bmp<Rgb888>, scaled_width: u32, scaled_height: u32) -> Vec<u16> {
+    let Size { width, height } = bmp.size();
+    let mut sums = vec![[0u32; 4]; (scaled_width * scaled_height) as usize];
+    for Pixel(point, color) in bmp.pixels() {
+        let x = point.x as u64 * scaled_width as u64 / width as u64;
+        let y = point.y as u64 * scaled_height as u64 / height as u64;
+        let sum = &mut sums[(y * scaled_width as u64 + x) as usize];
+        sum[0] += color.r() as u32;
+        sum[1] += color.g() as u32;
+        sum[2] += color.b() as u32;
+        sum[3] += 1;
+    }
+
+    sums.into_iter().map(|[r, g, b, count]| {
+        let count = count.max(1);
+        Rgb565::from(Rgb888::new((r / count) as u8, (g / count) as u8, (b / count) as u8)).into_storage()
+    }).collect()
+}
+
+// the whole image as it'll be shown, for the native format
+pub fn bmp_to_rgb565(bytes: &[u8]) -> Option<Rgb565Image> {
+    let bmp: Bmp<Rgb888> = Bmp::from_slice(bytes).ok()?;
+    let Size { width, height } = bmp.size();
+    let mut pixels = vec![0u16; (width * height) as usize];
+    for Pixel(point, color) in bmp.pixels() {
+        pixels[(point.y as u32 * width + point.x as u32) as usize] = Rgb565::from(color).into_storage();
+    }
+    Some(Rgb565Image::new(width, height, pixels))
+}
+
+#[derive(Debug)]
+pub struct ImportedAnimation {
+    pub width: u32,
+    pub height: u32,
+    // 0 = forever
+    pub plays: u32,
+    pub frames: Vec<AnimationFrame>,
+}
+
+// animated GIF and APNG images, with every frame already drawn over the ones before it. anything that isn't
+// one of those, or only has a single frame, isn't an animation
+pub fn import_animation(bytes: &[u8]) -> Option<ImportedAnimation> {
+    let (loop_count, frames): (LoopCount, Frames) = if bytes.starts_with(b"GIF8") {
+        let decoder = GifDecoder::new(Cursor::new(bytes)).ok()?;
+        (decoder.loop_count(), decoder.into_frames())
+    } else {
+        let decoder = PngDecoder::new(Cursor::new(bytes)).ok()?;
+        if !decoder.is_apng().ok()? {
+            return None;
+        }
+        let decoder = decoder.apng().ok()?;
+        (decoder.loop_count(), decoder.into_frames())
+    };
+    let frames = frames.collect_frames().ok()?;
+    if frames.len() < 2 {
+        return None;
+    }
+
+    let (width, height) = frames[0].buffer().dimensions();
+    let frames = frames.iter().map(|frame| {
+        // transparent parts show the black behind the image
+        let pixels = frame.buffer().pixels().map(|pixel| {
+            let [r, g, b, a] = pixel.0.map(|channel| channel as u32);
+            let [r, g, b] = [r, g, b].map(|channel| (channel * a / 255) as u8);
+            Rgb565::from(Rgb888::new(r, g, b)).into_storage()
+        }).collect();
+        // like browsers, treat delays too short to be meant literally as the usual tenth of a second
+        let (numerator, denominator) = frame.delay().numer_denom_ms();
+        let duration = match numerator / denominator.max(1) {
+            0..=10 => 100,
+            duration => duration,
+        };
+        AnimationFrame { image: Rgb565Image::new(width, height, pixels), duration }
+    }).collect();
+
+    let plays = match loop_count {
+        LoopCount::Infinite => 0,
+        LoopCount::Finite(plays) => plays.get(),
+    };
+    Some(ImportedAnimation { width, height, plays, frames })
+}
@@ -1,104 +1,366 @@
-use pocket_knife_file_format::{Archivable, ReadError, CreateError, OpenError};
+use crate::image::*;
 
-use std::{fs::File, path::Path, io::{Read, Write, Seek, SeekFrom}, fmt::Debug};
+use pocket_knife_file_format::{level_filename, playlist_filename, Animation, Archivable, Compression, ImageInfo, ImageLevel, Metadata, PixelFormat, Playlist, PlaylistItem, Rgb565Encoding, Rgb565Image, ThumbnailImage, TiledImage, ANIMATION_MEDIA_TYPE, PATH_SEPARATOR, PLAYLIST_MEDIA_TYPE, RGB565_MEDIA_TYPE, TILED_MEDIA_TYPE};
+
+use serde::Deserialize;
+use std::{cell::{OnceCell, RefCell}, collections::BTreeMap, fs, path::{Component, Path}, io::{self, Read}, rc::Rc, time::UNIX_EPOCH};
 
 #[derive(Debug)]
 pub struct Error(pub String);
 
 #[derive(Debug)]
-pub struct ArchiveFile(pub File);
+pub struct InputFile {
+    pub path: Box<Path>,
+    // keep the path relative to this directory in the archive, instead of just the file name
+    pub base: Option<Box<Path>>,
+    pub options: PackOptions,
+    // a downscaled copy of the image (see levels.rs in the file format) instead of the file itself
+    pub level: Option<u32>,
+    // shared by the image and its levels, and let go of once the last of them has been written
+    source: Rc<OnceCell<Source>>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PackOptions {
+    pub compression: Compression,
+    pub tags: BTreeMap<String, String>,
+    // largest thumbnail width and height, no thumbnails if missing
+    pub thumbnail_size: Option<u32>,
+    // store BMP images in the native RGB565 format instead, encoded like this
+    pub rgb565: Option<Rgb565Encoding>,
+    // tile converted images bigger than the screen, this many pixels square
+    pub tile_size: Option<u32>,
+    // add downscaled copies of BMP images bigger than the screen, halving until they fit
+    pub levels: bool,
+    // store animated GIF and APNG images as animations, which the frontend can play
+    pub animations: bool,
+}
+
+// a file being packed, read once and decoded at most once into each thing it's stored as, however many times the
+// builder asks about it
+#[derive(Debug)]
+struct Source {
+    bytes: Vec<u8>,
+    converted: OnceCell<Option<Rc<Rgb565Image>>>,
+    levels: RefCell<BTreeMap<u32, Option<Rc<Rgb565Image>>>>,
+    animation: OnceCell<Option<Rc<ImportedAnimation>>>,
+}
+
+// a playlist from a manifest, to go in the archive as an entry of its own (see playlist.rs in the file format)
+#[derive(Debug)]
+pub struct PlaylistInput {
+    pub name: String,
+    pub playlist: Playlist,
+}
 
+// everything that can go in an archive, so files and playlists can be packed together
 #[derive(Debug)]
-pub struct InputFile(pub Box<Path>);
+pub enum Input {
+    File(InputFile),
+    Playlist(PlaylistInput),
+}
 
-impl Archivable<ArchiveFile> for InputFile {
-    fn filename(&self) -> Result<String, Error> {
-        self.0.file_name()
-            .and_then(|filename| filename.to_str())
-            .map(String::from)
-            .ok_or(Error(format!("invalid filename {:?}", self.0)))
+// playlist manifests are TOML, with a table for each playlist by the name it's stored under:
+//
+//   [comic]
+//   title = "The Comic"
+//   items = ["comic/page1.bmp", { filename = "comic/page2.bmp", title = "Page two" }]
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ManifestPlaylist {
+    title: String,
+    items: Vec<ManifestItem>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum ManifestItem {
+    Filename(String),
+    Titled { filename: String, title: String },
+}
+
+pub fn read_manifest(path: &Path) -> Result<Vec<PlaylistInput>, Error> {
+    let manifest: BTreeMap<String, ManifestPlaylist> = toml::from_str(&fs::read_to_string(path)?)
+        .map_err(|err| Error(format!("invalid manifest {:?}: {}", path, err)))?;
+    Ok(manifest.into_iter()
+        .map(|(name, playlist)| {
+            let items = playlist.items.into_iter()
+                .map(|item| match item {
+                    ManifestItem::Filename(filename) => PlaylistItem { filename, title: None },
+                    ManifestItem::Titled { filename, title } => PlaylistItem { filename, title: Some(title) },
+                })
+                .collect();
+            PlaylistInput { name, playlist: Playlist { title: playlist.title, items } }
+        })
+        .collect())
+}
+
+impl <T: embedded_io::ErrorType<Error = io::Error>> Archivable<T> for Input {
+    fn filename(&self) -> Result<String, io::Error> {
+        match self {
+            Input::File(input_file) => Archivable::<T>::filename(input_file),
+            Input::Playlist(playlist) => Archivable::<T>::filename(playlist),
+        }
     }
 
-    fn write_into(&self, archive: &mut ArchiveFile) -> Result<u64, Error> {
-        let mut input_file = File::open(self.0.clone())?;
-        Ok(std::io::copy(&mut input_file, &mut archive.0)?)
+    fn write_into<W: embedded_io::Write<Error = io::Error>>(&self, archive: &mut W) -> Result<u64, io::Error> {
+        match self {
+            Input::File(input_file) => Archivable::<T>::write_into(input_file, archive),
+            Input::Playlist(playlist) => Archivable::<T>::write_into(playlist, archive),
+        }
     }
-}
 
-impl embedded_io::Read for ArchiveFile {
-    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
-        Ok(self.0.read(buf)?)
+    fn compression(&self) -> Compression {
+        match self {
+            Input::File(input_file) => Archivable::<T>::compression(input_file),
+            Input::Playlist(playlist) => Archivable::<T>::compression(playlist),
+        }
     }
 
-    fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), embedded_io::ReadExactError<Error>> {
-        self.0.read_exact(buf).map_err(|err| embedded_io::ReadExactError::Other(err.into()))
+    fn metadata(&self) -> Result<Metadata, io::Error> {
+        match self {
+            Input::File(input_file) => Archivable::<T>::metadata(input_file),
+            Input::Playlist(playlist) => Archivable::<T>::metadata(playlist),
+        }
+    }
+
+    fn thumbnail(&self) -> Result<Option<ThumbnailImage>, io::Error> {
+        match self {
+            Input::File(input_file) => Archivable::<T>::thumbnail(input_file),
+            Input::Playlist(playlist) => Archivable::<T>::thumbnail(playlist),
+        }
     }
 }
 
-impl embedded_io::Write for ArchiveFile {
-    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
-        Ok(self.0.write(buf)?)
+impl <T: embedded_io::ErrorType<Error = io::Error>> Archivable<T> for PlaylistInput {
+    fn filename(&self) -> Result<String, io::Error> {
+        Ok(playlist_filename(&self.name))
     }
 
-    fn write_all(&mut self, buf: &[u8]) -> Result<(), Error> {
-        Ok(self.0.write_all(buf)?)
+    fn write_into<W: embedded_io::Write<Error = io::Error>>(&self, archive: &mut W) -> Result<u64, io::Error> {
+        let bytes = self.playlist.encode().map_err(io::Error::other)?;
+        archive.write_all(&bytes)?;
+        Ok(bytes.len() as u64)
     }
 
-    fn flush(&mut self) -> Result<(), Error> {
-        Ok(self.0.flush()?)
+    fn metadata(&self) -> Result<Metadata, io::Error> {
+        Ok(Metadata { media_type: Some(PLAYLIST_MEDIA_TYPE.into()), ..Metadata::default() })
     }
 }
 
-impl embedded_io::Seek for ArchiveFile {
-    fn seek(&mut self, pos: embedded_io::SeekFrom) -> Result<u64, Error> {
-        Ok(self.0.seek(SeekFrom::from(pos))?)
+impl <T: embedded_io::ErrorType<Error = io::Error>> Archivable<T> for InputFile {
+    fn filename(&self) -> Result<String, io::Error> {
+        self.archive_filename()
     }
 
-    fn rewind(&mut self) -> Result<(), Error> {
-        Ok(self.0.rewind()?)
+    fn write_into<W: embedded_io::Write<Error = io::Error>>(&self, archive: &mut W) -> Result<u64, io::Error> {
+        let payload = match (self.animation()?, self.converted()?) {
+            (Some(animation), _) => {
+                let encoding = self.options.rgb565.unwrap_or_default();
+                let bytes = Animation::encode(animation.width, animation.height, animation.plays, &animation.frames, encoding)
+                    .map_err(io::Error::other)?;
+                Some(bytes)
+            },
+            (None, Some(image)) => Some(self.encode(&image)?),
+            (None, None) if self.level.is_some() => {
+                let message = format!("couldn't decode {:?} for its levels", self.path);
+                return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+            },
+            (None, None) => None,
+        };
+        let bytes = match &payload {
+            Some(bytes) => bytes,
+            None => &self.source()?.bytes,
+        };
+        archive.write_all(bytes)?;
+        Ok(bytes.len() as u64)
     }
 
-    fn stream_position(&mut self) -> Result<u64, Error> {
-        Ok(self.0.stream_position()?)
+    fn compression(&self) -> Compression {
+        // tiles and frames are read by seeking into the entry, which compressed entries can't do
+        if self.animation().ok().flatten().is_some() {
+            return Compression::None;
+        }
+        match self.converted().ok().flatten().and_then(|image| self.tile_size_for(image.width, image.height)) {
+            Some(_) => Compression::None,
+            None => self.options.compression,
+        }
     }
-}
 
-impl embedded_io::ErrorType for ArchiveFile {
-    type Error = Error;
-}
+    fn metadata(&self) -> Result<Metadata, io::Error> {
+        let file_metadata = fs::metadata(&self.path)?;
+        let created = file_metadata.created().or_else(|_| file_metadata.modified()).ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map(|duration| duration.as_secs() as i64);
+        let (media_type, image) = match (self.animation()?, self.converted()?) {
+            (Some(animation), _) => {
+                let image = ImageInfo { width: animation.width, height: animation.height, pixel_format: PixelFormat::Rgb565 };
+                (Some(ANIMATION_MEDIA_TYPE.into()), Some(image))
+            },
+            (None, Some(converted)) => {
+                let image = ImageInfo { width: converted.width, height: converted.height, pixel_format: PixelFormat::Rgb565 };
+                let media_type = match self.tile_size_for(converted.width, converted.height) {
+                    Some(_) => TILED_MEDIA_TYPE,
+                    None => RGB565_MEDIA_TYPE,
+                };
+                (Some(media_type.into()), Some(image))
+            },
+            (None, None) => {
+                let image = bmp_image_info(&self.source()?.bytes);
+                (image.map(|_| BMP_MEDIA_TYPE.into()), image)
+            },
+        };
+        // levels only link back through their filename, everything else about them is the image's
+        if self.level.is_some() {
+            return Ok(Metadata { media_type, image, created, ..Metadata::default() });
+        }
+        Ok(Metadata {
+            media_type,
+            image,
+            created,
+            tags: self.options.tags.clone(),
+            levels: self.image_levels()?,
+        })
+    }
 
-impl embedded_io::Error for Error {
-    fn kind(&self) -> embedded_io::ErrorKind {
-        embedded_io::ErrorKind::Other
+    fn thumbnail(&self) -> Result<Option<ThumbnailImage>, io::Error> {
+        let Some(thumbnail_size) = self.options.thumbnail_size.filter(|_| self.level.is_none()) else { return Ok(None) };
+        Ok(bmp_thumbnail(&self.source()?.bytes, thumbnail_size))
     }
 }
 
-impl From<std::io::Error> for Error {
-    fn from(err: std::io::Error) -> Self {
-        Error(format!("{:?}", err))
+impl InputFile {
+    pub fn new(path: Box<Path>, base: Option<Box<Path>>, options: PackOptions) -> InputFile {
+        InputFile { path, base, options, level: None, source: Rc::default() }
     }
-}
 
-impl From<embedded_io::ReadExactError<Error>> for Error {
-    fn from(err: embedded_io::ReadExactError<Error>) -> Self {
-        Error(format!("{:?}", err))
+    // where the file goes in the archive
+    pub fn archive_filename(&self) -> Result<String, io::Error> {
+        let filename = self.image_filename()?;
+        match self.level {
+            Some(level) => Ok(level_filename(&filename, level)),
+            None => Ok(filename),
+        }
+    }
+
+    // the image's levels as inputs of their own, to go in the archive alongside it
+    pub fn levels(&self) -> Result<Vec<InputFile>, io::Error> {
+        let count = self.level_sizes()?.len() as u32;
+        Ok((1..=count)
+            .map(|level| InputFile {
+                path: self.path.clone(),
+                base: self.base.clone(),
+                options: self.options.clone(),
+                level: Some(level),
+                source: self.source.clone(),
+            })
+            .collect())
+    }
+
+    // the filename of the image itself, whether this is the image or one of its levels
+    fn image_filename(&self) -> Result<String, io::Error> {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidInput, format!("invalid filename {:?}", self.path));
+        match &self.base {
+            None => self.path.file_name()
+                .and_then(|filename| filename.to_str())
+                .map(String::from)
+                .ok_or_else(invalid),
+            Some(base) => self.path.strip_prefix(base).map_err(|_| invalid())?
+                .components()
+                .map(|component| match component {
+                    Component::Normal(name) => name.to_str().ok_or_else(invalid),
+                    _ => Err(invalid()),
+                })
+                .collect::<Result<Vec<_>, _>>()
+                .map(|components| components.join(&PATH_SEPARATOR.to_string())),
+        }
+    }
+
+    // what gets stored instead of the file itself, when it's a BMP image being converted or a level of one.
+    // levels are always converted, so they can be shown without decoding
+    fn converted(&self) -> Result<Option<Rc<Rgb565Image>>, io::Error> {
+        match self.level {
+            Some(level) => Ok(self.source()?.level(level)),
+            None if self.options.rgb565.is_some() => Ok(self.source()?.converted()),
+            None => Ok(None),
+        }
+    }
+
+    fn source(&self) -> Result<&Source, io::Error> {
+        if let Some(source) = self.source.get() {
+            return Ok(source);
+        }
+        let source = Source {
+            bytes: fs::read(&self.path)?,
+            converted: OnceCell::new(),
+            levels: RefCell::default(),
+            animation: OnceCell::new(),
+        };
+        Ok(self.source.get_or_init(|| source))
+    }
+
+    fn encode(&self, image: &Rgb565Image) -> Result<Vec<u8>, io::Error> {
+        let encoding = self.options.rgb565.unwrap_or_default();
+        match self.tile_size_for(image.width, image.height) {
+            Some(tile_size) => TiledImage::encode(image, tile_size, tile_size, encoding).map_err(io::Error::other),
+            None => Ok(image.encode(encoding)),
+        }
+    }
+
+    fn image_levels(&self) -> Result<Vec<ImageLevel>, io::Error> {
+        let filename = self.image_filename()?;
+        Ok(self.level_sizes()?.into_iter().zip(1..)
+            .map(|((width, height), level)| ImageLevel { filename: level_filename(&filename, level), width, height })
+            .collect())
     }
-}
 
-impl <E: Debug> From<ReadError<E>> for Error where Error: From<E> {
-    fn from(err: ReadError<E>) -> Self {
-        Error(format!("{:?}", err))
+    // only BMP images get levels, and levels don't get levels of their own. the sizes come from the header alone,
+    // so the levels can be listed before anything is decoded
+    fn level_sizes(&self) -> Result<Vec<(u32, u32)>, io::Error> {
+        if !self.options.levels || self.level.is_some() {
+            return Ok(Vec::new());
+        }
+        let mut header = Vec::with_capacity(BMP_HEADER_LENGTH);
+        fs::File::open(&self.path)?.take(BMP_HEADER_LENGTH as u64).read_to_end(&mut header)?;
+        Ok(bmp_size(&header).map_or(Vec::new(), |(width, height)| level_sizes(width, height)))
+    }
+
+    fn animation(&self) -> Result<Option<Rc<ImportedAnimation>>, io::Error> {
+        if !self.options.animations || self.level.is_some() {
+            return Ok(None);
+        }
+        Ok(self.source()?.animation())
+    }
+
+    // images that fit on the screen are always read whole, so tiling them wouldn't save anything
+    fn tile_size_for(&self, width: u32, height: u32) -> Option<u32> {
+        self.options.tile_size.filter(|_| width > SCREEN_WIDTH || height > SCREEN_HEIGHT)
     }
 }
 
-impl <E: Debug> From<CreateError<E>> for Error where Error: From<E> {
-    fn from(err: CreateError<E>) -> Self {
-        Error(format!("{:?}", err))
+impl Source {
+    fn converted(&self) -> Option<Rc<Rgb565Image>> {
+        self.converted.get_or_init(|| bmp_to_rgb565(&self.bytes).map(Rc::new)).clone()
+    }
+
+    fn level(&self, level: u32) -> Option<Rc<Rgb565Image>> {
+        self.levels.borrow_mut().entry(level).or_insert_with(|| bmp_level(&self.bytes, level).map(Rc::new)).clone()
+    }
+
+    fn animation(&self) -> Option<Rc<ImportedAnimation>> {
+        self.animation.get_or_init(|| import_animation(&self.bytes).map(Rc::new)).clone()
     }
 }
 
-impl <E: Debug> From<OpenError<E>> for Error where Error: From<E> {
-    fn from(err: OpenError<E>) -> Self {
-        Error(format!("{:?}", err))
+// keeps the whole chain of sources, so the message says both what failed and why
+impl <E: std::error::Error> From<E> for Error {
+    fn from(err: E) -> Self {
+        let mut message = err.to_string();
+        let mut source = err.source();
+        while let Some(err) = source {
+            message += &format!(": {}", err);
+            source = err.source();
+        }
+        Error(message)
     }
 }
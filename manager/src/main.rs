@@ -1,71 +1,517 @@
-#![feature(ascii_char)]
-
+mod image;
 mod io;
 pub use io::*;
 
-use pocket_knife_file_format::FileTable;
+use pocket_knife_file_format::{Child, Compression, CreateOptions, FileTable, Header, Rgb565Encoding, StdArchive, VerifyError, VolumeBuilder, VolumeSet, PATH_SEPARATOR};
 
-use std::io::Write;
+use clap::Parser;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, File};
+use std::process;
+use std::io::{stdout, BufWriter, Seek, Write};
 use std::path::Path;
-use std::{env, fs};
+
+#[derive(Parser)]
+#[command(about = "Packs and inspects Pocket Knife archives")]
+enum Command {
+    /// Pack files into a new archive, directories keep their structure inside the archive
+    Pack {
+        /// Where to write the archive, or - to stream it to stdout
+        archive: String,
+        inputs: Vec<String>,
+        /// Compress files with LZ4 wherever that makes them smaller
+        #[arg(long)]
+        compress: bool,
+        /// Tag every packed file with a KEY=VALUE pair, can be given more than once
+        #[arg(long = "tag", value_parser = parse_tag)]
+        tags: Vec<(String, String)>,
+        /// Store an RGB565 thumbnail of every BMP image, scaled to fit within this many pixels square
+        #[arg(long)]
+        thumbnail_size: Option<u32>,
+        /// Convert BMP images to the native RGB565 format, which the frontend can show without decoding
+        #[arg(long)]
+        rgb565: bool,
+        /// Run-length encode converted RGB565 images
+        #[arg(long, requires = "rgb565")]
+        rle: bool,
+        /// Store converted RGB565 images bigger than the screen as tiles this many pixels square, so the frontend
+        /// only reads the part being shown
+        #[arg(long, requires = "rgb565")]
+        tile_size: Option<u32>,
+        /// Add copies of BMP images bigger than the screen at half, a quarter and so on of their size, down to
+        /// one that fits, so the frontend can zoom out without shrinking the whole image
+        #[arg(long)]
+        levels: bool,
+        /// Convert animated GIF and APNG images to animations the frontend can play, with frames in the native
+        /// RGB565 format
+        #[arg(long)]
+        animations: bool,
+        /// Add the playlists defined in this TOML manifest, each a titled list of files in the archive in the order
+        /// the frontend should show them
+        #[arg(long)]
+        playlists: Option<String>,
+        /// Start every file at a multiple of this many bytes, e.g. 512 or 4096, must be a power of two
+        #[arg(long, default_value_t = 0)]
+        align: u32,
+        /// Store identical files and thumbnails only once
+        #[arg(long)]
+        dedup: bool,
+        /// Write the file table as an index that can be searched without decoding it
+        #[arg(long)]
+        index: bool,
+        /// Split the files into volumes of at most this many bytes, written next to the archive as ARCHIVE.1,
+        /// ARCHIVE.2 and so on, leaving only the file table in the archive itself. The Pocket can only open 4
+        /// volumes
+        #[arg(long)]
+        max_volume_size: Option<u64>,
+    },
+    /// Add files to an existing archive, without rewriting the files already in it
+    Add {
+        archive: String,
+        inputs: Vec<String>,
+        /// Compress files with LZ4 wherever that makes them smaller
+        #[arg(long)]
+        compress: bool,
+        /// Tag every added file with a KEY=VALUE pair, can be given more than once
+        #[arg(long = "tag", value_parser = parse_tag)]
+        tags: Vec<(String, String)>,
+        /// Store an RGB565 thumbnail of every BMP image, scaled to fit within this many pixels square
+        #[arg(long)]
+        thumbnail_size: Option<u32>,
+        /// Convert BMP images to the native RGB565 format, which the frontend can show without decoding
+        #[arg(long)]
+        rgb565: bool,
+        /// Run-length encode converted RGB565 images
+        #[arg(long, requires = "rgb565")]
+        rle: bool,
+        /// Store converted RGB565 images bigger than the screen as tiles this many pixels square, so the frontend
+        /// only reads the part being shown
+        #[arg(long, requires = "rgb565")]
+        tile_size: Option<u32>,
+        /// Add copies of BMP images bigger than the screen at half, a quarter and so on of their size, down to
+        /// one that fits, so the frontend can zoom out without shrinking the whole image
+        #[arg(long)]
+        levels: bool,
+        /// Convert animated GIF and APNG images to animations the frontend can play, with frames in the native
+        /// RGB565 format
+        #[arg(long)]
+        animations: bool,
+        /// Add the playlists defined in this TOML manifest, each a titled list of files in the archive in the order
+        /// the frontend should show them
+        #[arg(long)]
+        playlists: Option<String>,
+    },
+    /// Remove files from an archive, the space they took up is only reclaimed by compacting
+    Remove {
+        archive: String,
+        files: Vec<String>,
+    },
+    /// Rename a file in an archive
+    Rename {
+        archive: String,
+        from: String,
+        to: String,
+    },
+    /// Copy an archive into a new one without any unused space
+    Compact {
+        archive: String,
+        output: String,
+    },
+    /// Print an archive's header and file table
+    Info {
+        archive: String,
+    },
+    /// Extract files from an archive into the current directory
+    Unpack {
+        archive: String,
+        outputs: Vec<String>,
+    },
+    /// List the files and subdirectories in a directory of an archive
+    List {
+        archive: String,
+        #[arg(default_value = "")]
+        directory: String,
+    },
+    /// Check every file in an archive against its checksum
+    Verify {
+        archive: String,
+    },
+}
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
+    let result = match Command::parse() {
+        Command::Pack { archive, inputs, compress, tags, thumbnail_size, rgb565, rle, tile_size, levels, animations, playlists, align, dedup, index, max_volume_size } => {
+            let options = PackOptions {
+                compression: if compress { Compression::Lz4 } else { Compression::None },
+                tags: tags.into_iter().collect(),
+                thumbnail_size,
+                rgb565: rgb565_encoding(rgb565, rle),
+                tile_size,
+                levels,
+                animations,
+            };
+            let create_options = CreateOptions { alignment: align, deduplicate: dedup, index };
+            match max_volume_size {
+                Some(max_volume_size) => pack_volumes(&archive, &inputs, playlists.as_ref(), &options, create_options, max_volume_size),
+                None => pack(&archive, &inputs, playlists.as_ref(), &options, create_options),
+            }
+        },
+        Command::Add { archive, inputs, compress, tags, thumbnail_size, rgb565, rle, tile_size, levels, animations, playlists } => {
+            let options = PackOptions {
+                compression: if compress { Compression::Lz4 } else { Compression::None },
+                tags: tags.into_iter().collect(),
+                thumbnail_size,
+                rgb565: rgb565_encoding(rgb565, rle),
+                tile_size,
+                levels,
+                animations,
+            };
+            add(&archive, &inputs, playlists.as_ref(), &options)
+        },
+        Command::Remove { archive, files } => remove(&archive, &files),
+        Command::Rename { archive, from, to } => rename(&archive, from, to),
+        Command::Compact { archive, output } => compact(&archive, &output),
+        Command::Info { archive } => info(&archive),
+        Command::Unpack { archive, outputs } => unpack(&archive, &outputs),
+        Command::List { archive, directory } => list(&archive, &directory),
+        Command::Verify { archive } => verify(&archive),
+    };
+    if let Err(err) = result {
+        eprintln!("error: {}", err.0);
+        process::exit(1);
+    }
+}
 
-    match args[1].as_str() {
-        "pack" => pack(&args[2], &args[3..]).unwrap(),
-        "info" => info(&args[2]).unwrap(),
-        "unpack" => unpack(&args[2], &args[3..]).unwrap(),
-        _ => panic!("unsupported command"),
+fn rgb565_encoding(rgb565: bool, rle: bool) -> Option<Rgb565Encoding> {
+    match (rgb565, rle) {
+        (false, _) => None,
+        (true, false) => Some(Rgb565Encoding::Raw),
+        (true, true) => Some(Rgb565Encoding::Rle),
     }
 }
 
-fn pack(archive_name: &String, input_path_strs: &[String]) -> Result<(), Error> {
-    let mut archive = ArchiveFile(fs::OpenOptions::new()
+fn parse_tag(tag: &str) -> Result<(String, String), String> {
+    tag.split_once('=')
+        .map(|(key, value)| (key.into(), value.into()))
+        .ok_or(format!("expected KEY=VALUE, got {:?}", tag))
+}
+
+fn pack(
+    archive_name: &String,
+    input_path_strs: &[String],
+    manifest: Option<&String>,
+    options: &PackOptions,
+    create_options: CreateOptions,
+) -> Result<(), Error> {
+    let input_paths = with_playlists(input_files(input_path_strs, options)?, manifest, &FileTable(BTreeMap::new()))?;
+
+    if archive_name == "-" {
+        let mut archive = StdArchive(BufWriter::new(stdout()));
+        let file_table = FileTable::create_streaming(&mut archive, input_paths, create_options)?;
+        archive.0.flush()?;
+        // stdout is taken up by the archive
+        eprintln!("{:?}", file_table.0);
+        eprintln!("{}", shared_summary(&file_table));
+        return Ok(());
+    }
+
+    let mut archive = StdArchive(fs::OpenOptions::new()
         .write(true)
         .create_new(true)
-        .open(archive_name).unwrap());
+        .open(archive_name)?);
 
-    let input_paths: Vec<_> = input_path_strs.iter().map(|input_path_str| {
-        InputFile(Box::from(Path::new(input_path_str)))
-    }).collect();
+    let file_table = FileTable::create_with_options(&mut archive, input_paths, create_options)?;
 
-    let file_table = FileTable::create(&mut archive, input_paths.as_slice())?;
+    println!("{:?}", file_table.0);
+    println!("{}", shared_summary(&file_table));
+
+    Ok(())
+}
+
+// how many volumes the Pocket's backend can open, one for each data slot after the archive's (see data.json there)
+const POCKET_VOLUMES: u16 = 4;
+
+fn pack_volumes(
+    archive_path: &String,
+    input_path_strs: &[String],
+    manifest: Option<&String>,
+    options: &PackOptions,
+    create_options: CreateOptions,
+    max_volume_size: u64,
+) -> Result<(), Error> {
+    if archive_path == "-" {
+        return Err(Error("archives split into volumes can't be streamed".into()));
+    }
+    let input_paths = with_playlists(input_files(input_path_strs, options)?, manifest, &FileTable(BTreeMap::new()))?;
+
+    let create_volume = |number| fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(volume_path(archive_path, number))
+        .map(StdArchive);
+    let mut builder = VolumeBuilder::new(max_volume_size, create_options, create_volume)?;
+    for input_path in input_paths {
+        builder.add_entry(&input_path)?;
+    }
+    let file_table = builder.finish()?;
 
     println!("{:?}", file_table.0);
+    println!("{} volumes", file_table.volume_count());
+    println!("{}", shared_summary(&file_table));
+    // volume_count includes the archive itself
+    if file_table.volume_count() > POCKET_VOLUMES as usize + 1 {
+        eprintln!(
+            "warning: the archive is split into {} volumes, but the Pocket only has data slots for {}",
+            file_table.volume_count() - 1,
+            POCKET_VOLUMES,
+        );
+    }
 
     Ok(())
 }
 
-fn info(archive_path: &String) -> Result<(), Error> {
-    let mut archive = ArchiveFile(fs::OpenOptions::new()
+// an archive's volumes go next to it, numbered from 1. volume 0 is the archive itself
+fn volume_path(archive_path: &str, number: u16) -> String {
+    match number {
+        0 => archive_path.into(),
+        _ => format!("{}.{}", archive_path, number),
+    }
+}
+
+// opens an archive along with any volumes it's split into
+fn open_volumes(archive_path: &str) -> Result<(VolumeSet<StdArchive<File>>, FileTable), Error> {
+    let open_volume = |number| File::open(volume_path(archive_path, number)).map(StdArchive);
+    Ok(VolumeSet::open(open_volume(0)?, open_volume)?)
+}
+
+fn add(archive_path: &String, input_path_strs: &[String], manifest: Option<&String>, options: &PackOptions) -> Result<(), Error> {
+    let mut archive = StdArchive(fs::OpenOptions::new()
         .read(true)
-        .create_new(false)
+        .write(true)
         .open(archive_path)?
     );
 
-    let file_table = FileTable::read(&mut archive)?;
+    let mut file_table = FileTable::read(&mut archive)?;
+    let input_paths = with_playlists(input_files(input_path_strs, options)?, manifest, &file_table)?;
+    file_table.append(&mut archive, input_paths)?;
 
     println!("{:?}", file_table.0);
 
     Ok(())
 }
 
-fn unpack(archive_path: &String, output_path_strs: &[String]) -> Result<(), Error> {
-    let mut archive = ArchiveFile(fs::OpenOptions::new()
+fn remove(archive_path: &String, filenames: &[String]) -> Result<(), Error> {
+    let mut archive = StdArchive(fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(archive_path)?
+    );
+
+    let mut file_table = FileTable::read(&mut archive)?;
+    for filename in filenames {
+        // an image's levels are no use without it
+        let levels: Vec<String> = file_table.0.get(filename).into_iter()
+            .flat_map(|entry| entry.metadata.levels.iter())
+            .map(|level| level.filename.clone())
+            .filter(|level| file_table.0.contains_key(level))
+            .collect();
+        file_table.remove(&mut archive, filename.clone())?;
+        for level in levels {
+            file_table.remove(&mut archive, level)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn rename(archive_path: &String, from: String, to: String) -> Result<(), Error> {
+    let mut archive = StdArchive(fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(archive_path)?
+    );
+
+    let mut file_table = FileTable::read(&mut archive)?;
+    file_table.rename(&mut archive, from, to)?;
+
+    Ok(())
+}
+
+fn compact(archive_path: &String, output_path: &String) -> Result<(), Error> {
+    let mut archive = StdArchive(fs::OpenOptions::new()
+        .read(true)
+        .open(archive_path)?
+    );
+    let mut output = StdArchive(fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(output_path)?
+    );
+
+    let file_table = FileTable::read(&mut archive)?;
+    let compacted = file_table.compact(&mut archive, &mut output)?;
+
+    println!("{:?}", compacted.0);
+
+    Ok(())
+}
+
+fn shared_summary(file_table: &FileTable) -> String {
+    let shared_entries = file_table.shared_entries();
+    let shared_files: usize = shared_entries.iter().map(|group| group.len()).sum();
+    format!(
+        "{} files share their data with another, saving {} bytes",
+        shared_files,
+        file_table.shared_length(),
+    )
+}
+
+fn input_files(input_path_strs: &[String], options: &PackOptions) -> Result<Vec<InputFile>, Error> {
+    let mut input_paths = Vec::new();
+    for input_path_str in input_path_strs {
+        let input_path = Path::new(input_path_str);
+        if input_path.is_dir() {
+            // keep the directory's own name as the top level inside the archive
+            let directory = input_path.canonicalize()?;
+            let base = directory.parent().unwrap_or(&directory);
+            add_directory(&mut input_paths, &directory, base, options)?;
+        } else {
+            input_paths.push(InputFile::new(Box::from(input_path), None, options.clone()));
+        }
+    }
+    // levels go in as entries of their own, right after their image
+    let mut with_levels = Vec::new();
+    for input_path in input_paths {
+        let levels = input_path.levels()?;
+        with_levels.push(input_path);
+        with_levels.extend(levels);
+    }
+    Ok(with_levels)
+}
+
+// the files followed by the playlists in the manifest, if there is one. every playlist item has to be one of the
+// files or already in the archive
+fn with_playlists(input_files: Vec<InputFile>, manifest: Option<&String>, file_table: &FileTable) -> Result<Vec<Input>, Error> {
+    let playlists = match manifest {
+        Some(manifest) => read_manifest(Path::new(manifest))?,
+        None => Vec::new(),
+    };
+    let filenames = input_files.iter()
+        .map(InputFile::archive_filename)
+        .collect::<Result<BTreeSet<_>, _>>()?;
+    for playlist in playlists.iter() {
+        let missing = playlist.playlist.items.iter()
+            .find(|item| !filenames.contains(&item.filename) && !file_table.0.contains_key(&item.filename));
+        if let Some(item) = missing {
+            return Err(Error(format!("playlist {:?} lists {:?}, which isn't in the archive", playlist.name, item.filename)));
+        }
+    }
+    Ok(input_files.into_iter().map(Input::File)
+        .chain(playlists.into_iter().map(Input::Playlist))
+        .collect())
+}
+
+fn add_directory(
+    input_paths: &mut Vec<InputFile>,
+    directory: &Path,
+    base: &Path,
+    options: &PackOptions,
+) -> Result<(), Error> {
+    let mut dir_entries = fs::read_dir(directory)?.collect::<Result<Vec<_>, _>>()?;
+    dir_entries.sort_by_key(|dir_entry| dir_entry.path());
+    for dir_entry in dir_entries {
+        let path = dir_entry.path();
+        // file_type doesn't follow links, links to directories are skipped so a loop of them can't recurse forever
+        let file_type = dir_entry.file_type()?;
+        if file_type.is_dir() {
+            add_directory(input_paths, &path, base, options)?;
+        } else if file_type.is_symlink() && path.is_dir() {
+            // stderr, since the archive might be going to stdout
+            eprintln!("skipping {:?}, a link to a directory", path);
+        } else {
+            input_paths.push(InputFile::new(Box::from(path), Some(Box::from(base)), options.clone()));
+        }
+    }
+    Ok(())
+}
+
+fn info(archive_path: &String) -> Result<(), Error> {
+    let mut archive = StdArchive(fs::OpenOptions::new()
         .read(true)
         .create_new(false)
         .open(archive_path)?
     );
 
+    let mut header = Header::read(&mut archive)?;
+    if header.has_trailer() {
+        header.read_trailer(&mut archive)?;
+    }
+    archive.0.rewind()?;
     let file_table = FileTable::read(&mut archive)?;
 
+    println!("{:?}", header);
+    println!("{:?}", file_table.0);
+    if file_table.volume_count() > 1 {
+        println!("{} volumes", file_table.volume_count());
+    }
+    for group in file_table.shared_entries() {
+        println!("shared: {}", group.join(", "));
+    }
+    println!("{}", shared_summary(&file_table));
+
+    Ok(())
+}
+
+fn unpack(archive_path: &str, output_path_strs: &[String]) -> Result<(), Error> {
+    let (mut volumes, file_table) = open_volumes(archive_path)?;
+
     for output_path_str in output_path_strs {
+        if let Some(parent) = Path::new(output_path_str).parent() {
+            fs::create_dir_all(parent)?;
+        }
         fs::OpenOptions::new()
             .write(true)
             .create_new(true)
             .open(output_path_str)?
-            .write_all(&file_table.open_file(&mut archive, output_path_str.clone())?)?;
+            .write_all(&volumes.open_file(&file_table, output_path_str.clone())?)?;
+    }
+
+    Ok(())
+}
+
+fn list(archive_path: &String, directory: &str) -> Result<(), Error> {
+    let mut archive = StdArchive(fs::OpenOptions::new()
+        .read(true)
+        .create_new(false)
+        .open(archive_path)?
+    );
+
+    let file_table = FileTable::read(&mut archive)?;
+
+    if !file_table.is_directory(directory) {
+        return Err(Error(format!("no such directory {:?}", directory)));
+    }
+
+    for (name, child) in file_table.children(directory) {
+        match child {
+            Child::File(entry) => println!("{}\t{}", name, entry.decompressed_length),
+            Child::Directory => println!("{}{}", name, PATH_SEPARATOR),
+        }
+    }
+
+    Ok(())
+}
+
+fn verify(archive_path: &str) -> Result<(), Error> {
+    let (mut volumes, file_table) = open_volumes(archive_path)?;
+
+    match volumes.verify(&file_table) {
+        Err(VerifyError::CorruptEntries(filenames)) => {
+            for filename in filenames {
+                println!("corrupt: {}", filename);
+            }
+        },
+        result => result?,
     }
 
     Ok(())